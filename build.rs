@@ -0,0 +1,38 @@
+// the default translation is compiled into the binary, so check it here the same way any
+// translation is checked when it's loaded, and fail the build if anything's wrong with it,
+// rather than finding out at runtime
+
+use std::fs;
+
+// the checks themselves are the library's, with just the parts of it they need
+#[allow(dead_code)]
+#[path = "src/tables.rs"]
+mod tables;
+#[allow(dead_code)]
+#[path = "src/translation_file.rs"]
+mod translation_file;
+#[allow(dead_code)]
+#[path = "src/validation.rs"]
+mod validation;
+
+const TRANSLATION: &str = "data/wilhelm_baynes.json";
+
+fn main() {
+    for path in [TRANSLATION, "data/hexagrams_schema.json", "src/tables.rs", "src/translation_file.rs", "src/validation.rs"] {
+        println!("cargo::rerun-if-changed={}", path);
+    }
+
+    let text = fs::read_to_string(TRANSLATION).unwrap_or_else(|e| panic!("can't read {}: {}", TRANSLATION, e));
+    let json: serde_json::Value = serde_json::from_str(&text).unwrap_or_else(|e| panic!("{} isn't valid JSON: {}", TRANSLATION, e));
+
+    let mut problems = validation::schema_problems(&json);
+    if problems.is_empty() {
+        let data: translation_file::IChingData = serde_json::from_value(json)
+            .unwrap_or_else(|e| panic!("{} can't be read: {}", TRANSLATION, e));
+        problems = validation::semantic_problems(&data);
+    }
+    if !problems.is_empty() {
+        let problems: Vec<String> = problems.iter().map(|problem| format!("  {}", problem)).collect();
+        panic!("{} has problems:\n{}", TRANSLATION, problems.join("\n"));
+    }
+}
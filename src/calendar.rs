@@ -0,0 +1,290 @@
+//! Conversion from the Gregorian calendar to the Chinese lunisolar calendar.
+//!
+//! There's no table here, everything is worked out from the positions of the sun and moon,
+//! so it works offline for any year. The astronomy is the low precision version from
+//! Jean Meeus' Astronomical Algorithms, as used in Ho Ngoc Duc's lunar calendar algorithm,
+//! which is plenty to get the day of each new moon and solar term right.
+//!
+//! The rules:
+//!  - a month starts on the day of the new moon (in China, UTC+8)
+//!  - the month containing the winter solstice is always the 11th month
+//!  - if there are 13 new moons between one 11th month and the next, the first month
+//!    that contains no principal solar term (zhongqi) is a leap month and repeats the
+//!    number of the month before it
+
+use std::f64::consts::PI;
+
+/// Months and solar terms are reckoned in China Standard Time
+const CHINA_UTC_OFFSET_HOURS: f64 = 8.0;
+
+/// Average length of a lunation in days
+const SYNODIC_MONTH: f64 = 29.530588853;
+
+/// Julian day of the new moon on 1900-01-01, which is new moon number 0
+const NEW_MOON_EPOCH: f64 = 2415021.076998695;
+
+/// The twelve earthly branches. Index 0 is Zi, the first branch.
+pub const EARTHLY_BRANCHES: [(&str, &str, &str); 12] = [
+    ("Zi", "子", "Rat"),
+    ("Chou", "丑", "Ox"),
+    ("Yin", "寅", "Tiger"),
+    ("Mao", "卯", "Rabbit"),
+    ("Chen", "辰", "Dragon"),
+    ("Si", "巳", "Snake"),
+    ("Wu", "午", "Horse"),
+    ("Wei", "未", "Goat"),
+    ("Shen", "申", "Monkey"),
+    ("You", "酉", "Rooster"),
+    ("Xu", "戌", "Dog"),
+    ("Hai", "亥", "Pig"),
+];
+
+/// A date in the Chinese lunisolar calendar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LunarDate {
+    /// The Gregorian year in which this lunar year began
+    pub year: i32,
+    /// 1 to 12
+    pub month: u8,
+    /// true if this is the leap month repeating `month`
+    pub leap_month: bool,
+    /// 1 to 30
+    pub day: u8,
+}
+
+impl LunarDate {
+    /// The lunar date of a day in the Gregorian calendar
+    pub fn from_gregorian(year: i32, month: u32, day: u32) -> Self {
+        let day_number = julian_day_number(year, month, day);
+
+        let k = ((day_number as f64 - NEW_MOON_EPOCH) / SYNODIC_MONTH).floor() as i64;
+        let mut month_start = new_moon_day(k + 1);
+        if month_start > day_number {
+            month_start = new_moon_day(k);
+        }
+
+        // find the 11th months either side of the date
+        let mut lunar_year = year;
+        let mut a11 = lunar_month_11(year);
+        let mut b11 = a11;
+        if a11 >= month_start {
+            a11 = lunar_month_11(year - 1);
+        } else {
+            lunar_year = year + 1;
+            b11 = lunar_month_11(year + 1);
+        }
+
+        let day = (day_number - month_start + 1) as u8;
+        let diff = (month_start - a11) / 29;
+        let mut leap_month = false;
+        let mut month = diff + 11;
+
+        // 13 months between the 11th months, so one of them is a leap month
+        if b11 - a11 > 365 {
+            let leap_month_diff = leap_month_offset(a11);
+            if diff >= leap_month_diff {
+                month = diff + 10;
+                leap_month = diff == leap_month_diff;
+            }
+        }
+        if month > 12 {
+            month -= 12;
+        }
+        // the 11th and 12th months belong to the year before
+        if month >= 11 && diff < 4 {
+            lunar_year -= 1;
+        }
+
+        LunarDate { year: lunar_year, month: month as u8, leap_month, day }
+    }
+
+    /// Index into EARTHLY_BRANCHES for the year. 1984 was a Zi (rat) year.
+    pub fn year_branch(&self) -> usize {
+        (self.year - 1984).rem_euclid(12) as usize
+    }
+}
+
+/// A moment as the Chinese calendar counts it: the lunar date and the double-hour
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChineseTime {
+    /// The lunar date of the civil day
+    pub date: LunarDate,
+    /// index into EARTHLY_BRANCHES
+    pub hour_branch: usize,
+}
+
+impl ChineseTime {
+    /// From a local date and time. The lunar date is that of the civil date, so
+    /// the late Zi hour (23:00 to midnight) still counts as the same day.
+    pub fn from_datetime(datetime: &chrono::NaiveDateTime) -> Self {
+        use chrono::{Datelike, Timelike};
+
+        let date = LunarDate::from_gregorian(datetime.year(), datetime.month(), datetime.day());
+        ChineseTime { date, hour_branch: hour_branch(datetime.hour()) }
+    }
+}
+
+/// Index into EARTHLY_BRANCHES for the double-hour (shichen) containing the given hour of the day.
+/// The Zi hour runs from 23:00 to 00:59, Chou from 01:00 to 02:59 and so on.
+pub fn hour_branch(hour: u32) -> usize {
+    (hour.div_ceil(2) % 12) as usize
+}
+
+/// Julian day number of a date in the (proleptic) Gregorian calendar
+fn julian_day_number(year: i32, month: u32, day: u32) -> i64 {
+    let a = (14 - month as i64) / 12;
+    let y = year as i64 + 4800 - a;
+    let m = month as i64 + 12 * a - 3;
+    day as i64 + (153 * m + 2) / 5 + 365 * y + y / 4 - y / 100 + y / 400 - 32045
+}
+
+/// Julian day (TT corrected to UT) of new moon number k, counted from January 1900
+fn new_moon(k: i64) -> f64 {
+    let k = k as f64;
+    let t = k / 1236.85; // centuries since 1900
+    let t2 = t * t;
+    let t3 = t2 * t;
+    let dr = PI / 180.0;
+
+    let mut jd = 2415020.75933 + 29.53058868 * k + 0.0001178 * t2 - 0.000000155 * t3;
+    jd += 0.00033 * ((166.56 + 132.87 * t - 0.009173 * t2) * dr).sin();
+
+    // mean anomalies of the sun and moon, and the moon's argument of latitude
+    let m = 359.2242 + 29.10535608 * k - 0.0000333 * t2 - 0.00000347 * t3;
+    let mpr = 306.0253 + 385.81691806 * k + 0.0107306 * t2 + 0.00001236 * t3;
+    let f = 21.2964 + 390.67050646 * k - 0.0016528 * t2 - 0.00000239 * t3;
+
+    let mut c1 = (0.1734 - 0.000393 * t) * (m * dr).sin() + 0.0021 * (2.0 * dr * m).sin();
+    c1 = c1 - 0.4068 * (mpr * dr).sin() + 0.0161 * (dr * 2.0 * mpr).sin();
+    c1 -= 0.0004 * (dr * 3.0 * mpr).sin();
+    c1 = c1 + 0.0104 * (dr * 2.0 * f).sin() - 0.0051 * (dr * (m + mpr)).sin();
+    c1 = c1 - 0.0074 * (dr * (m - mpr)).sin() + 0.0004 * (dr * (2.0 * f + m)).sin();
+    c1 = c1 - 0.0004 * (dr * (2.0 * f - m)).sin() - 0.0006 * (dr * (2.0 * f + mpr)).sin();
+    c1 = c1 + 0.0010 * (dr * (2.0 * f - mpr)).sin() + 0.0005 * (dr * (2.0 * mpr + m)).sin();
+
+    // difference between dynamical time and universal time, in days
+    let delta_t = if t < -11.0 {
+        0.001 + 0.000839 * t + 0.0002261 * t2 - 0.00000845 * t3 - 0.000000081 * t * t3
+    } else {
+        -0.000278 + 0.000265 * t + 0.000262 * t2
+    };
+
+    jd + c1 - delta_t
+}
+
+/// Day number in China of new moon number k
+fn new_moon_day(k: i64) -> i64 {
+    (new_moon(k) + 0.5 + CHINA_UTC_OFFSET_HOURS / 24.0).floor() as i64
+}
+
+/// The sun's apparent longitude in radians (0 to 2 pi) at the given Julian day
+fn sun_longitude(jd: f64) -> f64 {
+    let t = (jd - 2451545.0) / 36525.0; // centuries since J2000
+    let t2 = t * t;
+    let dr = PI / 180.0;
+
+    let m = 357.52910 + 35999.05030 * t - 0.0001559 * t2 - 0.00000048 * t * t2;
+    let l0 = 280.46645 + 36000.76983 * t + 0.0003032 * t2;
+    let mut dl = (1.914600 - 0.004817 * t - 0.000014 * t2) * (dr * m).sin();
+    dl += (0.019993 - 0.000101 * t) * (dr * 2.0 * m).sin() + 0.000290 * (dr * 3.0 * m).sin();
+
+    ((l0 + dl) * dr).rem_euclid(2.0 * PI)
+}
+
+/// Which of the twelve 30 degree sectors the sun is in at the start of the given day in China.
+/// Each principal solar term begins a sector; the winter solstice begins sector 9.
+fn sun_sector(day_number: i64) -> i64 {
+    let jd = day_number as f64 - 0.5 - CHINA_UTC_OFFSET_HOURS / 24.0;
+    (sun_longitude(jd) / PI * 6.0).floor() as i64
+}
+
+/// Day number on which the 11th month (the one containing the winter solstice) of the given year begins
+fn lunar_month_11(year: i32) -> i64 {
+    let offset = julian_day_number(year, 12, 31) as f64 - 2415021.0;
+    let k = (offset / SYNODIC_MONTH).floor() as i64;
+    let new_moon = new_moon_day(k);
+
+    // if the sun is already past the solstice, the 11th month started a month earlier
+    if sun_sector(new_moon) >= 9 {
+        new_moon_day(k - 1)
+    } else {
+        new_moon
+    }
+}
+
+/// How many months after the 11th month starting on a11 the leap month falls.
+/// It's the first month during which the sun doesn't enter a new sector.
+fn leap_month_offset(a11: i64) -> i64 {
+    let k = ((a11 as f64 - NEW_MOON_EPOCH) / SYNODIC_MONTH + 0.5).floor() as i64;
+    let mut i = 1;
+    let mut arc = sun_sector(new_moon_day(k + i));
+    loop {
+        let last = arc;
+        i += 1;
+        arc = sun_sector(new_moon_day(k + i));
+        if arc == last || i >= 14 {
+            break;
+        }
+    }
+    i - 1
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lunar(year: i32, month: u8, leap_month: bool, day: u8) -> LunarDate {
+        LunarDate { year, month, leap_month, day }
+    }
+
+    #[test]
+    fn test_new_years_days() {
+        assert_eq!(LunarDate::from_gregorian(1985, 2, 20), lunar(1985, 1, false, 1));
+        assert_eq!(LunarDate::from_gregorian(2000, 2, 5), lunar(2000, 1, false, 1));
+        assert_eq!(LunarDate::from_gregorian(2023, 1, 22), lunar(2023, 1, false, 1));
+        assert_eq!(LunarDate::from_gregorian(2024, 2, 10), lunar(2024, 1, false, 1));
+        assert_eq!(LunarDate::from_gregorian(2025, 1, 29), lunar(2025, 1, false, 1));
+
+        // the day before new year is the end of the 12th month of the previous year
+        assert_eq!(LunarDate::from_gregorian(2024, 2, 9), lunar(2023, 12, false, 30));
+    }
+
+    #[test]
+    fn test_leap_months() {
+        assert_eq!(LunarDate::from_gregorian(2020, 5, 23), lunar(2020, 4, true, 1));
+        assert_eq!(LunarDate::from_gregorian(2023, 3, 22), lunar(2023, 2, true, 1));
+        assert_eq!(LunarDate::from_gregorian(2023, 4, 20), lunar(2023, 3, false, 1));
+        assert_eq!(LunarDate::from_gregorian(2025, 7, 25), lunar(2025, 6, true, 1));
+    }
+
+    #[test]
+    fn test_other_dates() {
+        // mid-autumn festival is always the 15th of the 8th month
+        assert_eq!(LunarDate::from_gregorian(2024, 9, 17), lunar(2024, 8, false, 15));
+        assert_eq!(LunarDate::from_gregorian(1999, 9, 24), lunar(1999, 8, false, 15));
+        // the founding of the People's Republic
+        assert_eq!(LunarDate::from_gregorian(1949, 10, 1), lunar(1949, 8, false, 10));
+    }
+
+    #[test]
+    fn test_branches() {
+        assert_eq!(EARTHLY_BRANCHES[lunar(2024, 1, false, 1).year_branch()].2, "Dragon");
+        assert_eq!(EARTHLY_BRANCHES[lunar(1984, 1, false, 1).year_branch()].0, "Zi");
+        assert_eq!(EARTHLY_BRANCHES[lunar(1900, 1, false, 1).year_branch()].2, "Rat");
+
+        assert_eq!(hour_branch(23), 0);
+        assert_eq!(hour_branch(0), 0);
+        assert_eq!(hour_branch(1), 1);
+        assert_eq!(hour_branch(12), 6); // noon is in the Wu hour
+        assert_eq!(hour_branch(22), 11);
+    }
+
+    #[test]
+    fn test_chinese_time() {
+        let datetime = chrono::NaiveDate::from_ymd_opt(2024, 9, 17).unwrap().and_hms_opt(21, 30, 0).unwrap();
+        let time = ChineseTime::from_datetime(&datetime);
+        assert_eq!(time.date, lunar(2024, 8, false, 15));
+        assert_eq!(EARTHLY_BRANCHES[time.hour_branch].0, "Hai");
+    }
+}
@@ -0,0 +1,305 @@
+//! Casting methods: coins, yarrow stalks, custom line probabilities, Plum Blossom numbers
+//! and lines cast by hand
+
+use std::fmt;
+
+use crate::calendar::{ChineseTime, EARTHLY_BRANCHES};
+use crate::error::IChingError;
+use crate::line::Line;
+
+/// The stalks in a full bundle, one of which is set aside before casting
+pub const YARROW_STALKS: u32 = 50;
+
+/// One of the three changes that make up a line: the heap sizes after dividing
+/// the stalks and hanging one between the fingers, and what was left when each heap
+/// was counted off by fours.
+#[derive(Debug, Clone, Copy)]
+pub struct YarrowChange {
+    /// How many stalks there were to divide
+    pub stalks: u32,
+    /// The left heap
+    pub left: u32,
+    /// The right heap, after the stalk between the fingers was taken from it
+    pub right: u32,
+    /// What was left of the left heap after counting it off by fours, 1 to 4
+    pub left_remainder: u32,
+    /// What was left of the right heap, 1 to 4, or 0 if the heap was empty
+    pub right_remainder: u32,
+}
+
+impl YarrowChange {
+    /// The stalk between the fingers plus both remainders
+    pub fn set_aside(&self) -> u32 {
+        1 + self.left_remainder + self.right_remainder
+    }
+}
+
+/// The three changes for one line and the stalks remaining at the end
+#[derive(Debug, Clone)]
+pub struct YarrowLineRecord {
+    /// The three changes, in order
+    pub changes: [YarrowChange; 3],
+    /// The stalks left after the third change: 24, 28, 32 or 36
+    pub remaining: u32,
+    /// The line they make, the remaining stalks divided by four
+    pub line: Line,
+}
+
+/// Every change of a simulated yarrow stalk casting, bottom line first.
+/// Six lines of three changes each makes the traditional eighteen changes.
+#[derive(Debug, Clone, Default)]
+pub struct YarrowTranscript {
+    /// One record per line, bottom first
+    pub lines: Vec<YarrowLineRecord>,
+}
+
+impl fmt::Display for YarrowTranscript {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "HOW THE LINES WERE CAST")?;
+
+        for (i, record) in self.lines.iter().enumerate() {
+            writeln!(f, "Line {}: of {} stalks one is set aside, leaving {}",
+                i + 1, YARROW_STALKS, YARROW_STALKS - 1)?;
+
+            for (n, change) in record.changes.iter().enumerate() {
+                writeln!(f,
+                    "  change {}: {} stalks divided into {} and {} with 1 between the fingers; remainders {} and {}, {} set aside, {} left",
+                    n + 1, change.stalks, change.left, change.right + 1,
+                    change.left_remainder, change.right_remainder,
+                    change.set_aside(), change.stalks - change.set_aside())?;
+            }
+
+            writeln!(f, "  {} stalks / 4 = {}, {}\n",
+                record.remaining, record.line.value(), record.line.describe())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Probabilities of 6, 7, 8 and 9 for a single line, in that order
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineDistribution {
+    /// For 6, 7, 8 and 9, adding up to 1
+    pub probabilities: [f64; 4],
+}
+
+impl LineDistribution {
+    /// The lines the probabilities are for, in the same order
+    pub const LINES: [Line; 4] = [Line::ChangingYin, Line::StaticYang, Line::StaticYin, Line::ChangingYang];
+
+    /// Three coins: 1/8, 3/8, 3/8, 1/8
+    pub const THREE_COINS: LineDistribution = LineDistribution { probabilities: [0.125, 0.375, 0.375, 0.125] };
+
+    /// Yarrow stalks, and also the four coin and sixteen marble methods: 1/16, 5/16, 7/16, 3/16
+    pub const YARROW: LineDistribution = LineDistribution { probabilities: [0.0625, 0.3125, 0.4375, 0.1875] };
+
+    /// Probabilities must not be negative and must add up to 1
+    pub fn new(probabilities: [f64; 4]) -> Result<Self, IChingError> {
+        if probabilities.iter().any(|p| !p.is_finite() || *p < 0.0) {
+            return Err(IChingError::InputError(format!("line probabilities {:?} must all be between 0 and 1", probabilities)));
+        }
+        let total: f64 = probabilities.iter().sum();
+        if (total - 1.0).abs() > 1e-9 {
+            return Err(IChingError::InputError(format!("line probabilities {:?} add up to {}, not 1", probabilities, total)));
+        }
+        Ok(LineDistribution { probabilities })
+    }
+
+    /// Parse four probabilities for 6, 7, 8 and 9 separated by commas, as decimals or fractions,
+    /// e.g. "0.125,0.375,0.375,0.125" or "1/16,5/16,7/16,3/16".
+    /// "coins" and "yarrow" give the usual distributions.
+    pub fn parse(input: &str) -> Result<Self, IChingError> {
+        match input.trim() {
+            "coins" => return Ok(LineDistribution::THREE_COINS),
+            "yarrow" | "four-coins" | "marbles" => return Ok(LineDistribution::YARROW),
+            _ => {}
+        }
+
+        let parse_probability = |text: &str| -> Result<f64, IChingError> {
+            let text = text.trim();
+            let invalid = || IChingError::InputError(format!("'{}' is not a probability", text));
+            match text.split_once('/') {
+                Some((numerator, denominator)) => {
+                    let numerator: f64 = numerator.trim().parse().map_err(|_| invalid())?;
+                    let denominator: f64 = denominator.trim().parse().map_err(|_| invalid())?;
+                    Ok(numerator / denominator)
+                }
+                None => text.parse().map_err(|_| invalid()),
+            }
+        };
+
+        let probabilities = input.split(',').map(parse_probability).collect::<Result<Vec<_>, _>>()?;
+        let count = probabilities.len();
+        let probabilities: [f64; 4] = probabilities.try_into().map_err(|_| {
+            IChingError::InputError(format!("expected 4 probabilities for 6, 7, 8 and 9, found {}", count))
+        })?;
+        LineDistribution::new(probabilities)
+    }
+
+    /// The probabilities to four places, e.g. "6: 0.1250, 7: 0.3750, 8: 0.3750, 9: 0.1250"
+    pub fn describe(&self) -> String {
+        let [six, seven, eight, nine] = self.probabilities;
+        format!("6: {:.4}, 7: {:.4}, 8: {:.4}, 9: {:.4}", six, seven, eight, nine)
+    }
+}
+
+/// How each line of the hexagram is obtained.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CastingMethod {
+    /// three coins, heads 3 and tails 2. Old yin and old yang are equally likely (1/8 each)
+    ThreeCoins,
+    /// the yarrow stalk probabilities (1/16, 5/16, 7/16, 3/16) sampled directly
+    YarrowStalks,
+    /// the 49 stalks are divided and counted off for every line
+    YarrowStalksSimulated,
+    /// any probabilities for 6, 7, 8 and 9
+    Distribution(LineDistribution),
+    /// Plum Blossom (Mei Hua Yi Shu): the whole hexagram comes from two numbers
+    /// instead of six random lines, see Hexagram::from_numbers
+    PlumBlossom {
+        /// Gives the upper trigram
+        first: u64,
+        /// Gives the lower trigram, and with the first the moving line
+        second: u64,
+    },
+    /// the lines were cast with real coins or stalks and typed in
+    Manual([Line; 6]),
+    /// Plum Blossom from the year branch, lunar month, lunar day and double-hour,
+    /// see Hexagram::from_time
+    PlumBlossomTime(ChineseTime),
+}
+
+impl CastingMethod {
+    /// Cast a single line, for the methods that work line by line (see uses_rng).
+    /// Plum Blossom and lines entered by hand give the whole hexagram at once, so there's no line to cast.
+    pub fn cast_line(&self, rng: &mut impl rand::Rng) -> Option<Line> {
+        match self {
+            CastingMethod::ThreeCoins => Some(Line::sample(&LineDistribution::THREE_COINS, rng)),
+            CastingMethod::YarrowStalks => Some(Line::generate_yarrow(rng)),
+            CastingMethod::YarrowStalksSimulated => Some(Line::generate_yarrow_simulated(rng)),
+            CastingMethod::Distribution(distribution) => Some(Line::sample(distribution, rng)),
+            CastingMethod::PlumBlossom { .. } | CastingMethod::PlumBlossomTime(_) | CastingMethod::Manual(_) => None,
+        }
+    }
+
+    /// Description for the reading, e.g. "yarrow stalks"
+    pub fn name(&self) -> String {
+        match self {
+            CastingMethod::ThreeCoins => "three coins".to_string(),
+            CastingMethod::YarrowStalks => "yarrow stalks".to_string(),
+            CastingMethod::YarrowStalksSimulated => "yarrow stalks (simulated)".to_string(),
+            CastingMethod::Distribution(distribution) => format!("line distribution {}", distribution.describe()),
+            CastingMethod::Manual(_) => "lines entered by hand".to_string(),
+            CastingMethod::PlumBlossom { first, second } => format!("plum blossom numbers {} and {}", first, second),
+            CastingMethod::PlumBlossomTime(time) => {
+                let (year_branch, year_char, animal) = EARTHLY_BRANCHES[time.date.year_branch()];
+                let (hour_branch, hour_char, _) = EARTHLY_BRANCHES[time.hour_branch];
+                format!("plum blossom time: {} year {} {} ({}), {}month {}, day {}, {} {} hour",
+                    time.date.year, year_branch, year_char, animal,
+                    if time.date.leap_month { "leap " } else { "" }, time.date.month, time.date.day,
+                    hour_branch, hour_char)
+            }
+        }
+    }
+
+    /// Whether the lines come from a random number generator, and so depend on the seed
+    pub fn uses_rng(&self) -> bool {
+        matches!(self, CastingMethod::ThreeCoins | CastingMethod::YarrowStalks | CastingMethod::YarrowStalksSimulated
+            | CastingMethod::Distribution(_))
+    }
+
+    /// Parse the name used on the command line
+    pub fn from_arg(arg: &str) -> Option<Self> {
+        match arg {
+            "coins" => Some(CastingMethod::ThreeCoins),
+            "yarrow" => Some(CastingMethod::YarrowStalks),
+            "yarrow-sim" => Some(CastingMethod::YarrowStalksSimulated),
+            _ => None
+        }
+    }
+
+    /// The name used on the command line, for the methods that have one
+    pub fn arg_name(&self) -> Option<&'static str> {
+        match self {
+            CastingMethod::ThreeCoins => Some("coins"),
+            CastingMethod::YarrowStalks => Some("yarrow"),
+            CastingMethod::YarrowStalksSimulated => Some("yarrow-sim"),
+            _ => None
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hexagram::Hexagram;
+
+    #[test]
+    fn test_line_distribution() {
+        use rand::SeedableRng;
+        use rand::rngs::StdRng;
+
+        assert_eq!(LineDistribution::parse("1/16, 5/16, 7/16, 3/16").unwrap(), LineDistribution::YARROW);
+        assert_eq!(LineDistribution::parse("0.125,0.375,0.375,0.125").unwrap(), LineDistribution::THREE_COINS);
+        assert_eq!(LineDistribution::parse("marbles").unwrap(), LineDistribution::YARROW);
+
+        // must add up to 1, not be negative, and have four entries
+        assert!(matches!(LineDistribution::parse("0.5,0.5,0.5,0.5"), Err(IChingError::InputError(_))));
+        assert!(matches!(LineDistribution::parse("-0.5,0.5,0.5,0.5"), Err(IChingError::InputError(_))));
+        assert!(matches!(LineDistribution::parse("0.5,0.5"), Err(IChingError::InputError(_))));
+        assert!(matches!(LineDistribution::parse("a,b,c,d"), Err(IChingError::InputError(_))));
+
+        // a distribution with only old yang only ever gives old yang
+        let only_nines = LineDistribution::new([0.0, 0.0, 0.0, 1.0]).unwrap();
+        let mut rng = StdRng::seed_from_u64(5);
+        assert!((0..100).all(|_| Line::sample(&only_nines, &mut rng) == Line::ChangingYang));
+
+        let method = CastingMethod::Distribution(only_nines);
+        assert_eq!(method.name(), "line distribution 6: 0.0000, 7: 0.0000, 8: 0.0000, 9: 1.0000");
+        assert_eq!(Hexagram::generate_present(method, &mut rng).king_wen_number, 1);
+        assert_eq!(method.cast_line(&mut rng), Some(Line::ChangingYang));
+
+        // three coins is just the coin distribution, so the same seed gives the same lines either way
+        let coins = Hexagram::generate_present(CastingMethod::ThreeCoins, &mut StdRng::seed_from_u64(9));
+        let distribution = CastingMethod::Distribution(LineDistribution::THREE_COINS);
+        assert_eq!(Hexagram::generate_present(distribution, &mut StdRng::seed_from_u64(9)).lines, coins.lines);
+
+        // the whole-hexagram methods have no single line to cast
+        assert_eq!(CastingMethod::PlumBlossom { first: 1, second: 2 }.cast_line(&mut rng), None);
+        assert_eq!(CastingMethod::Manual([Line::StaticYin; 6]).cast_line(&mut rng), None);
+    }
+
+    #[test]
+    fn test_yarrow_transcript() {
+        use rand::SeedableRng;
+        use rand::rngs::StdRng;
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let (hexagram, transcript) = Hexagram::generate_present_with_transcript(CastingMethod::YarrowStalksSimulated, &mut rng);
+        let transcript = transcript.unwrap();
+        assert_eq!(transcript.lines.len(), 6);
+
+        for (record, line) in transcript.lines.iter().zip(hexagram.lines.iter()) {
+            // every change starts with what the previous one left behind
+            assert_eq!(record.changes[0].stalks, 49);
+            assert_eq!(record.changes[1].stalks, 49 - record.changes[0].set_aside());
+            assert_eq!(record.changes[2].stalks, record.changes[1].stalks - record.changes[1].set_aside());
+            assert_eq!(record.remaining, record.changes[2].stalks - record.changes[2].set_aside());
+            assert_eq!(record.remaining / 4, line.value() as u32);
+
+            for change in &record.changes {
+                assert_eq!(change.left + change.right + 1, change.stalks);
+            }
+        }
+
+        let text = transcript.to_string();
+        assert!(text.contains("Line 6: of 50 stalks one is set aside, leaving 49"));
+        assert_eq!(text.matches("change ").count(), 18);
+
+        // the other methods have nothing to record
+        let (_, transcript) = Hexagram::generate_present_with_transcript(CastingMethod::YarrowStalks, &mut rng);
+        assert!(transcript.is_none());
+    }
+}
@@ -0,0 +1,118 @@
+//! Where the seed for a reading comes from.
+//!
+//! By default it's the operating system's random number generator, but the seed can also
+//! be mixed with the timing of the querent's own key presses, so that the act of asking
+//! has a hand in the outcome.
+
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, Write};
+use std::time::{Duration, Instant};
+
+/// Anything that can provide the seed for a divination
+pub trait EntropySource {
+    /// A seed for the random number generator
+    fn seed(&mut self) -> u64;
+}
+
+/// The operating system's random number generator
+pub struct OsEntropy;
+
+impl EntropySource for OsEntropy {
+    fn seed(&mut self) -> u64 {
+        rand::rng().next_u64()
+    }
+}
+
+/// The time between Enter presses, one for each line, mixed with randomness from the OS.
+/// Nanosecond timings are far too jittery for anyone to control, but it's still the
+/// querent's hand that sets them.
+pub struct KeystrokeEntropy {
+    timings: Vec<Duration>,
+    base: u64,
+}
+
+impl KeystrokeEntropy {
+    /// Asks for Enter to be pressed once per line, writing the prompts to `prompt`, and times each press.
+    /// Input that runs out first is an UnexpectedEof error.
+    pub fn collect(input: &mut impl BufRead, prompt: &mut impl Write, lines: usize) -> std::io::Result<Self> {
+        let mut timings = Vec::with_capacity(lines);
+        let mut last = Instant::now();
+
+        for i in 0..lines {
+            writeln!(prompt, "Press Enter to cast line {}...", i + 1)?;
+            prompt.flush()?;
+            let mut buffer = String::new();
+            if input.read_line(&mut buffer)? == 0 {
+                return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof,
+                    format!("input ended after {} of the {} presses", i, lines)));
+            }
+
+            let now = Instant::now();
+            timings.push(now - last);
+            last = now;
+        }
+
+        Ok(KeystrokeEntropy::from_timings(timings, rand::rng().next_u64()))
+    }
+
+    /// Timings recorded elsewhere, mixed with the given base value
+    pub fn from_timings(timings: Vec<Duration>, base: u64) -> Self {
+        KeystrokeEntropy { timings, base }
+    }
+}
+
+impl EntropySource for KeystrokeEntropy {
+    fn seed(&mut self) -> u64 {
+        let mut hasher = Sha256::new();
+        hasher.update(self.base.to_be_bytes());
+        for timing in &self.timings {
+            hasher.update(timing.as_nanos().to_be_bytes());
+        }
+        let digest = hasher.finalize();
+        u64::from_be_bytes(digest[..8].try_into().unwrap())
+    }
+}
+
+/// Always gives the same seed. For tests.
+pub struct MockEntropy(pub u64);
+
+impl EntropySource for MockEntropy {
+    fn seed(&mut self) -> u64 {
+        self.0
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keystroke_timings_change_the_seed() {
+        let timings = vec![Duration::from_millis(900), Duration::from_nanos(1_234_567)];
+        let seed = KeystrokeEntropy::from_timings(timings.clone(), 1).seed();
+
+        // deterministic given the same timings and base
+        assert_eq!(KeystrokeEntropy::from_timings(timings.clone(), 1).seed(), seed);
+
+        // but a nanosecond or a different base changes everything
+        let nudged = vec![Duration::from_millis(900), Duration::from_nanos(1_234_568)];
+        assert_ne!(KeystrokeEntropy::from_timings(nudged, 1).seed(), seed);
+        assert_ne!(KeystrokeEntropy::from_timings(timings, 2).seed(), seed);
+    }
+
+    #[test]
+    fn test_collect_reads_one_line_per_press() {
+        let mut input = std::io::Cursor::new("\n\n\n\n\n\n");
+        let mut prompts = Vec::new();
+        let entropy = KeystrokeEntropy::collect(&mut input, &mut prompts, 6).unwrap();
+        assert_eq!(entropy.timings.len(), 6);
+        let prompts = String::from_utf8(prompts).unwrap();
+        assert!(prompts.starts_with("Press Enter to cast line 1...\n"));
+        assert_eq!(prompts.lines().count(), 6);
+
+        let error = KeystrokeEntropy::collect(&mut std::io::Cursor::new("\n\n"), &mut std::io::sink(), 6).err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+}
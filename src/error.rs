@@ -0,0 +1,93 @@
+//! The crate's error type
+
+use std::fmt;
+
+use crate::validation::ValidationProblem;
+
+/// Error handling for JSON loading and for input that can't be cast
+#[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum IChingError {
+    /// A file couldn't be read
+    FileError(std::io::Error),
+    /// A file isn't JSON, or isn't the JSON expected
+    JsonError(serde_json::Error),
+    /// The JSON was read but doesn't make sense, e.g. a reading in a format this version can't read
+    DataError(String),
+    /// Everything wrong with a translation file, not just the first thing
+    ValidationError(Vec<ValidationProblem>),
+    /// Something given on the command line or typed in that can't be used
+    InputError(String),
+}
+
+impl fmt::Display for IChingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            // the underlying error is the source, so it isn't repeated here
+            IChingError::FileError(_) => write!(f, "File error"),
+            IChingError::JsonError(_) => write!(f, "JSON parsing error"),
+            IChingError::DataError(msg) => write!(f, "Data validation error: {}", msg),
+            IChingError::ValidationError(problems) => {
+                write!(f, "Data validation error: {} problem{}", problems.len(), if problems.len() == 1 { "" } else { "s" })?;
+                for problem in problems {
+                    write!(f, "\n  {}", problem)?;
+                }
+                Ok(())
+            }
+            IChingError::InputError(msg) => write!(f, "Invalid input: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for IChingError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IChingError::FileError(e) => Some(e),
+            IChingError::JsonError(e) => Some(e),
+            IChingError::DataError(_) | IChingError::ValidationError(_) | IChingError::InputError(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for IChingError {
+    fn from(error: std::io::Error) -> Self {
+        IChingError::FileError(error)
+    }
+}
+
+impl From<serde_json::Error> for IChingError {
+    fn from(error: serde_json::Error) -> Self {
+        IChingError::JsonError(error)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translation::{load_hexagram_data, IChingData, IChingTranslation};
+
+    #[test]
+    fn test_error_display_and_source() {
+        use std::error::Error;
+
+        let missing = load_hexagram_data("data/does_not_exist.json").unwrap_err();
+        assert!(matches!(missing, IChingError::FileError(_)));
+        assert_eq!(missing.to_string(), "File error");
+        assert!(missing.source().unwrap().downcast_ref::<std::io::Error>().is_some());
+
+        let json: IChingError = serde_json::from_str::<IChingData>("{").unwrap_err().into();
+        assert_eq!(json.to_string(), "JSON parsing error");
+        assert!(json.source().unwrap().downcast_ref::<serde_json::Error>().is_some());
+
+        let data = IChingError::DataError("Expected 64 hexagrams, found 3".to_string());
+        assert_eq!(format!("{}", data), "Data validation error: Expected 64 hexagrams, found 3");
+        assert!(data.source().is_none());
+
+        // composes with ? into a boxed error
+        fn load() -> Result<IChingTranslation, Box<dyn Error>> {
+            Ok(load_hexagram_data("data/does_not_exist.json")?)
+        }
+        assert!(load().is_err());
+    }
+}
@@ -0,0 +1,625 @@
+//! Hexagrams: the six lines, the King Wen sequence and the two trigrams
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::calendar::ChineseTime;
+use crate::casting::{CastingMethod, YarrowTranscript};
+use crate::error::IChingError;
+use crate::line::Line;
+use crate::render::shown_text;
+use crate::tables;
+use crate::translation::TextLookup;
+
+/// Six lines, bottom first, and the hexagram's place in the King Wen sequence.
+/// In JSON it's written out in full as a HexagramRecord.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(into = "HexagramRecord", try_from = "HexagramRecord")]
+pub struct Hexagram
+{
+    /// Lines are stored in the order generated, 
+    /// i.e. `lines[0]` is the bottom line of the hexagram. 
+    pub lines: [Line; 6],
+
+    /// King Wen sequence number, look it up from the table
+    pub king_wen_number: u8,
+
+}
+
+/// A hexagram as it's written in JSON, see data/reading_schema.json. Only the lines are
+/// needed to read it back; the rest is for whoever else reads the JSON, and has to agree with them.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct HexagramRecord {
+    /// 1 to 64
+    pub king_wen_number: u8,
+    /// The Fu Xi number counting from 0, with the bottom line as bit 0 and yang as 1
+    pub binary: u8,
+    /// 6, 7, 8 or 9, bottom first
+    pub lines: [Line; 6],
+    /// The name of the upper trigram, e.g. "Mountain"
+    pub upper_trigram: String,
+    /// The name of the lower trigram
+    pub lower_trigram: String,
+    /// Positions of the moving lines, 1 to 6 from the bottom
+    pub changing_lines: Vec<u8>,
+}
+
+impl From<Hexagram> for HexagramRecord {
+    fn from(hexagram: Hexagram) -> Self {
+        HexagramRecord {
+            king_wen_number: hexagram.king_wen_number,
+            binary: hexagram.binary(),
+            lines: hexagram.lines,
+            upper_trigram: Hexagram::TRIGRAMS[Hexagram::calculate_trigram(&hexagram.lines[3..6])].to_string(),
+            lower_trigram: Hexagram::TRIGRAMS[Hexagram::calculate_trigram(&hexagram.lines[0..3])].to_string(),
+            changing_lines: hexagram.get_changing_lines(),
+        }
+    }
+}
+
+impl TryFrom<HexagramRecord> for Hexagram {
+    type Error = IChingError;
+
+    fn try_from(record: HexagramRecord) -> Result<Self, Self::Error> {
+        let hexagram = Hexagram::from_lines(record.lines);
+        if HexagramRecord::from(hexagram.clone()) != record {
+            return Err(IChingError::DataError(format!(
+                "hexagram {} doesn't agree with its lines {}, which make hexagram {} with lines {:?} changing",
+                record.king_wen_number, record.lines.map(|line| line.value().to_string()).join(" "),
+                hexagram.king_wen_number, hexagram.get_changing_lines())));
+        }
+        Ok(hexagram)
+    }
+}
+
+impl Hexagram {
+    /// Generates a random hexagram, with changing and static lines. 
+    /// Use to generate the present hexagram
+    pub fn generate_present(method: CastingMethod, rng: &mut impl rand::Rng) -> Self {
+        Hexagram::generate_present_with_transcript(method, rng).0
+    }
+
+    /// Like generate_present, but when the stalks are simulated also returns
+    /// a record of every change so the casting can be walked through afterwards.
+    pub fn generate_present_with_transcript(method: CastingMethod, mut rng: &mut impl rand::Rng) -> (Self, Option<YarrowTranscript>) {
+        match method {
+            CastingMethod::PlumBlossom { first, second } => return (Hexagram::from_numbers(first, second), None),
+            CastingMethod::PlumBlossomTime(time) => return (Hexagram::from_time(&time), None),
+            CastingMethod::Manual(lines) => return (Hexagram::from_lines(lines), None),
+            _ => {}
+        }
+
+        let mut transcript = YarrowTranscript::default();
+
+        let lines: [Line; 6] = (0..6).map(|_| {
+            if method == CastingMethod::YarrowStalksSimulated {
+                let record = Line::generate_yarrow_recorded(&mut rng);
+                let line = record.line;
+                transcript.lines.push(record);
+                line
+            } else {
+                method.cast_line(&mut rng).expect("the whole-hexagram methods were handled above")
+            }
+        }).collect::<Vec<_>>().try_into().unwrap();
+
+        let number = Hexagram::calculate_number(&lines);
+        let transcript = if transcript.lines.is_empty() { None } else { Some(transcript) };
+        (Hexagram{lines, king_wen_number: number}, transcript)
+    }
+
+    /// A hexagram from lines that were cast some other way
+    pub fn from_lines(lines: [Line; 6]) -> Self {
+        let number = Hexagram::calculate_number(&lines);
+        Hexagram{lines, king_wen_number: number}
+    }
+
+    /// Parse six line values cast by hand, bottom line first. 
+    /// Anything goes between the digits, so "7 8 9 6 7 7", "7,8,9,6,7,7" and "9786 77" all work.
+    pub fn parse_lines(input: &str) -> Result<[Line; 6], IChingError> {
+        let lines = input.chars()
+            .filter(|c| !c.is_whitespace() && *c != ',')
+            .map(|c| match c.to_digit(10) {
+                Some(value) => Line::from_value(value as u8),
+                None => Err(IChingError::InputError(format!("'{}' is not a line value", c)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let count = lines.len();
+        lines.try_into().map_err(|_| {
+            IChingError::InputError(format!("expected 6 line values, found {}", count))
+        })
+    }
+
+    /// Plum Blossom casting. The first number picks the upper trigram and the second
+    /// the lower, both mod 8 in the Earlier Heaven order; their sum mod 6 is the moving line.
+    /// A remainder of zero counts as the last one (Kun for the trigrams, the top line for the sum).
+    /// Numbers as long as a phone number are fine; they're reduced before they're added.
+    pub fn from_numbers(first: u64, second: u64) -> Self {
+        Hexagram::from_plum_blossom(first, second, first % 6 + second % 6)
+    }
+
+    /// Time-based Plum Blossom casting. Year branch (Zi = 1), lunar month and lunar day
+    /// make the upper trigram; add the double-hour (Zi = 1) for the lower trigram and the moving line.
+    pub fn from_time(time: &ChineseTime) -> Self {
+        let date_sum = (time.date.year_branch() + 1) as u64 + time.date.month as u64 + time.date.day as u64;
+        let total = date_sum + (time.hour_branch + 1) as u64;
+        Hexagram::from_plum_blossom(date_sum, total, total)
+    }
+
+    /// Shared by both kinds of Plum Blossom casting: trigram numbers in the Earlier Heaven
+    /// order and the moving line, each taken mod 8 or mod 6
+    pub fn from_plum_blossom(upper_number: u64, lower_number: u64, line_number: u64) -> Self {
+        let upper = Hexagram::EARLIER_HEAVEN_TRIGRAMS[((upper_number % 8 + 7) % 8) as usize];
+        let lower = Hexagram::EARLIER_HEAVEN_TRIGRAMS[((lower_number % 8 + 7) % 8) as usize];
+        let moving_line = (line_number % 6 + 5) % 6; // 0 indexed from the bottom
+
+        let bits = (upper << 3) | lower;
+        let lines: [Line; 6] = std::array::from_fn(|i| {
+            let yang = bits & (1 << i) != 0;
+            match (yang, i == moving_line as usize) {
+                (true, true) => Line::ChangingYang,
+                (true, false) => Line::StaticYang,
+                (false, true) => Line::ChangingYin,
+                (false, false) => Line::StaticYin,
+            }
+        });
+
+        let number = Hexagram::calculate_number(&lines);
+        Hexagram{lines, king_wen_number: number}
+    }
+
+    /// A hexagram of static lines from two indexes into TRIGRAMS
+    pub fn from_trigrams(upper: usize, lower: usize) -> Self {
+        let bits = (upper << 3) | lower;
+        let lines: [Line; 6] = std::array::from_fn(|i| {
+            if bits & (1 << i) != 0 { Line::StaticYang } else { Line::StaticYin }
+        });
+        Hexagram::from_lines(lines)
+    }
+
+    /// The nuclear (inner) hexagram: lines 2-3-4 make the lower trigram and 3-4-5 the upper.
+    /// The lines of the nuclear hexagram are all static.
+    pub fn nuclear(&self) -> Hexagram {
+        let lower = Hexagram::calculate_trigram(&self.lines[1..4]);
+        let upper = Hexagram::calculate_trigram(&self.lines[2..5]);
+        Hexagram::from_trigrams(upper, lower)
+    }
+
+    /// The inverse (zong gua): the hexagram turned upside down, with all lines static
+    pub fn inverse(&self) -> Hexagram {
+        let mut lines = self.lines.map(|line| line.to_static());
+        lines.reverse();
+        Hexagram::from_lines(lines)
+    }
+
+    /// The opposite (cuo gua): every line turned into its opposite, all static
+    pub fn opposite(&self) -> Hexagram {
+        Hexagram::from_lines(self.lines.map(|line| line.opposite()))
+    }
+
+    /// The King Wen partner of this hexagram: 1 and 2, 3 and 4, and so on
+    pub fn king_wen_partner(&self) -> u8 {
+        if self.king_wen_number % 2 == 1 { self.king_wen_number + 1 } else { self.king_wen_number - 1 }
+    }
+
+    /// The Fu Xi number counting from 0: the bottom line is bit 0, and yang is 1
+    pub fn binary(&self) -> u8 {
+        (Hexagram::calculate_trigram(&self.lines[0..3]) | Hexagram::calculate_trigram(&self.lines[3..6]) << 3) as u8
+    }
+
+    /// Given the six lines, return the hexagram number (1 to 64) from the King Wen sequence.
+    pub fn calculate_number(lines: &[Line; 6]) -> u8
+    {
+        // first calculate the binary number. This is basically the Fu Xi number except 0 indexed.
+        let binary_num = lines.iter().enumerate().
+            fold(0, |acc, (i, line)|{
+                let bit = match line {
+                    Line::StaticYin | Line::ChangingYin => 0,
+                    Line::StaticYang | Line::ChangingYang => 1
+                };
+                acc | (bit << i)
+        });
+
+        // Look up the King Wen number from the table. 
+        Hexagram::KING_WEN_SEQUENCE[binary_num]
+    }
+
+    /// Given three lines, bottom first, return the trigram's index into TRIGRAMS
+    pub fn calculate_trigram(lines: &[Line]) -> usize
+    {
+        // range 0-7 for direct array indexing
+        lines.iter().enumerate().
+            fold(0, |acc, (i, line)|{
+                let bit = match line {
+                    Line::StaticYin | Line::ChangingYin => 0,
+                    Line::StaticYang | Line::ChangingYang => 1
+                };
+                acc | (bit << i)
+        })
+    }
+
+    /// Trigram names indexed by their binary value, bottom line = bit 0
+    pub const TRIGRAMS: [&str; 8] = tables::TRIGRAMS;
+
+    /// Earlier Heaven (Fu Xi) numbering of the trigrams, Qian = 1 through Kun = 8,
+    /// as indexes into TRIGRAMS
+    pub const EARLIER_HEAVEN_TRIGRAMS: [usize; 8] = [
+        7, // 1 qian, heaven
+        3, // 2 dui, lake
+        5, // 3 li, fire
+        1, // 4 zhen, thunder
+        6, // 5 xun, wind
+        2, // 6 kan, water
+        4, // 7 gen, mountain
+        0, // 8 kun, earth
+    ];
+
+    /// Complete mapping from binary index (0-63) to King Wen hexagram numbers (1-64).
+    /// Binary index is calculated from bottom line = bit 0, top line = bit 5
+    /// where Yin = 0, Yang = 1
+    pub const KING_WEN_SEQUENCE: [u8; 64] = tables::KING_WEN_SEQUENCE;
+
+    /// Returns a vector of the changing lines. 
+    /// Indexes are in the traditional I Ching order, i.e. 
+    /// the first line generated is the bottom line is 1, 
+    /// the top line is 6 which is the last line that was generated.
+    pub fn get_changing_lines(&self) -> Vec<u8> {
+        self.lines.iter().enumerate()
+            .filter_map(|(i, line)| {
+                if line.is_changing() { Some((i+1) as u8)} else {None}
+            }).collect()
+    }
+
+    /// Given a "present" hexagram, return the future hexagram
+    /// (with all changing lines changed)
+    pub fn change(&self) -> Option<Hexagram> {
+        if self.get_changing_lines().is_empty() {
+            return None;
+        }
+
+        let future_lines: [Line; 6] = self.lines.iter()
+            .map(|line| line.change_line())
+            .collect::<Vec<_>>().try_into().unwrap();
+
+        let future_number = Hexagram::calculate_number(&future_lines);
+        Some(Hexagram{lines: future_lines, king_wen_number: future_number})
+    }
+
+}
+
+/// How hexagrams are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderStyle {
+    /// Dashes, which work anywhere
+    #[default]
+    Ascii,
+    /// The hexagram's own character from the Unicode block at U+4DC0, and the trigram characters
+    Unicode,
+    /// Each line drawn wide with box-drawing characters, and the trigram characters
+    Box,
+}
+
+impl RenderStyle {
+    /// The style for a command line name: ascii, unicode or box
+    pub fn from_arg(name: &str) -> Option<Self> {
+        match name {
+            "ascii" => Some(RenderStyle::Ascii),
+            "unicode" => Some(RenderStyle::Unicode),
+            "box" => Some(RenderStyle::Box),
+            _ => None,
+        }
+    }
+}
+
+impl Hexagram {
+    /// The hexagram's character. The Unicode block is in King Wen order, starting with ䷀ at U+4DC0.
+    pub fn glyph(&self) -> char {
+        char::from_u32(0x4DC0 + self.king_wen_number as u32 - 1).expect("King Wen numbers are 1 to 64")
+    }
+
+    /// The character for a trigram, by its index into TRIGRAMS. The Unicode trigrams start
+    /// with ☰ at U+2630 and count up with a yin line as 1, top line first.
+    pub fn trigram_glyph(trigram: usize) -> char {
+        let yin = !trigram & 0b111;
+        let top_first = ((yin & 1) << 2) | (yin & 0b010) | (yin >> 2);
+        char::from_u32(0x2630 + top_first as u32).expect("trigrams are 0 to 7")
+    }
+
+    /// The lines, top first, and the trigrams
+    pub fn diagram(&self) -> String {
+        self.diagram_in(RenderStyle::Ascii)
+    }
+
+    /// The hexagram drawn in the given style
+    pub fn diagram_in(&self, style: RenderStyle) -> String {
+        let mut result = String::new();
+        let lower_trigram = Hexagram::calculate_trigram(&self.lines[0..3]);
+        let upper_trigram = Hexagram::calculate_trigram(&self.lines[3..6]);
+        let trigrams = format!("{} over {}", Hexagram::TRIGRAMS[upper_trigram], Hexagram::TRIGRAMS[lower_trigram]);
+
+        match style {
+            RenderStyle::Ascii => {
+                for (i, line) in self.lines.iter().rev().enumerate() {
+                    let line_num = 6-i;
+                    result.push_str(&format!("{} {}\n", line_num, line));
+                }
+                result.push_str(&format!("{}\n", trigrams));
+            }
+            RenderStyle::Unicode => {
+                result.push_str(&format!("{}  {}{}  {}\n", self.glyph(),
+                    Hexagram::trigram_glyph(upper_trigram), Hexagram::trigram_glyph(lower_trigram), trigrams));
+                // the one character can't show which lines are moving
+                let changing = self.get_changing_lines();
+                if !changing.is_empty() {
+                    let changing: Vec<String> = changing.iter().map(u8::to_string).collect();
+                    result.push_str(&format!("changing lines: {}\n", changing.join(", ")));
+                }
+            }
+            RenderStyle::Box => {
+                for (i, line) in self.lines.iter().rev().enumerate() {
+                    result.push_str(&format!("{} {}\n", 6-i, line.wide()));
+                }
+                result.push_str(&format!("{} over {}  {}\n",
+                    Hexagram::trigram_glyph(upper_trigram), Hexagram::trigram_glyph(lower_trigram), trigrams));
+            }
+        }
+        result
+    }
+
+    /// The hexagram with its name, judgement and image from the translation.
+    /// Whatever the translation doesn't have is left out or marked as unavailable.
+    pub fn describe(&self, texts: &TextLookup, style: RenderStyle) -> String {
+        let mut result = format!("HEXAGRAM {} \n", texts.title(self.king_wen_number));
+        result.push_str(&self.diagram_in(style));
+
+        result.push_str(&format!("THE JUDGEMENT\n{}\n\n", shown_text(texts, texts.judgement(self.king_wen_number))));
+        result.push_str(&format!("THE IMAGE\n{}\n\n", shown_text(texts, texts.image(self.king_wen_number))));
+
+        result
+    }
+}
+
+/// Just the structure; use describe for the texts
+impl fmt::Display for Hexagram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "HEXAGRAM {}\n{}", self.king_wen_number, self.diagram())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plum_blossom_numbers() {
+        // 1 and 1: heaven over heaven, 2 mod 6 moves the second line
+        let hexagram = Hexagram::from_numbers(1, 1);
+        assert_eq!(hexagram.king_wen_number, 1);
+        assert_eq!(hexagram.get_changing_lines(), vec![2]);
+
+        // 8 and 16 are both Kun, and 24 mod 6 == 0 moves the top line
+        let hexagram = Hexagram::from_numbers(8, 16);
+        assert_eq!(hexagram.king_wen_number, 2);
+        assert_eq!(hexagram.get_changing_lines(), vec![6]);
+
+        // 5 is Xun (wind) above, 6 is Kan (water) below: 59 Huan, 11 mod 6 moves line 5
+        let hexagram = Hexagram::from_numbers(5, 6);
+        assert_eq!(hexagram.king_wen_number, 59);
+        assert_eq!(hexagram.get_changing_lines(), vec![5]);
+        assert_eq!(Hexagram::TRIGRAMS[Hexagram::calculate_trigram(&hexagram.lines[3..6])], "Wind");
+        assert_eq!(Hexagram::TRIGRAMS[Hexagram::calculate_trigram(&hexagram.lines[0..3])], "Water");
+        assert_eq!(hexagram.change().unwrap().king_wen_number, 4);
+
+        // the largest numbers don't overflow: u64::MAX is 7 mod 8 (Gen, mountain) and 3 mod 6,
+        // and with 1 (Qian, heaven) the sum is 4 mod 6
+        let hexagram = Hexagram::from_numbers(u64::MAX, 1);
+        assert_eq!(hexagram.king_wen_number, 26);
+        assert_eq!(hexagram.get_changing_lines(), vec![4]);
+        let hexagram = Hexagram::from_numbers(u64::MAX, u64::MAX);
+        assert_eq!(hexagram.king_wen_number, 52);
+        assert_eq!(hexagram.get_changing_lines(), vec![6]);
+        // a phone number
+        assert_eq!(Hexagram::from_numbers(4155550123, 18005551234).king_wen_number,
+            Hexagram::from_numbers(4155550123 % 8, 18005551234 % 8).king_wen_number);
+
+        let mut rng = rand::rng();
+        let (cast, transcript) = Hexagram::generate_present_with_transcript(CastingMethod::PlumBlossom { first: 5, second: 6 }, &mut rng);
+        assert_eq!(cast.king_wen_number, 59);
+        assert!(transcript.is_none());
+    }
+
+    #[test]
+    fn test_plum_blossom_time() {
+        // 2024-02-10 at noon: Chen year (5), 1st month, 1st day, Wu hour (7)
+        let datetime = chrono::NaiveDate::from_ymd_opt(2024, 2, 10).unwrap().and_hms_opt(12, 0, 0).unwrap();
+        let time = ChineseTime::from_datetime(&datetime);
+
+        // 5 + 1 + 1 = 7 is Gen (mountain) above, 7 + 7 = 14 is Kan (water) below,
+        // 14 mod 6 moves line 2
+        let hexagram = Hexagram::from_time(&time);
+        assert_eq!(hexagram.king_wen_number, 4);
+        assert_eq!(hexagram.get_changing_lines(), vec![2]);
+
+        let method = CastingMethod::PlumBlossomTime(time);
+        assert_eq!(method.name(), "plum blossom time: 2024 year Chen 辰 (Dragon), month 1, day 1, Wu 午 hour");
+    }
+
+    #[test]
+    fn test_parse_lines() {
+        let expected = [Line::StaticYang, Line::StaticYin, Line::ChangingYang,
+                        Line::ChangingYin, Line::StaticYang, Line::StaticYang];
+        assert_eq!(Hexagram::parse_lines("7 8 9 6 7 7").unwrap(), expected);
+        assert_eq!(Hexagram::parse_lines("7,8,9,6,7,7").unwrap(), expected);
+        assert_eq!(Hexagram::parse_lines("789 677\n").unwrap(), expected);
+
+        let hexagram = Hexagram::from_lines(Hexagram::parse_lines("9786 77").unwrap());
+        assert_eq!(hexagram.get_changing_lines(), vec![1, 4]);
+
+        // wrong count, out of range, not a number
+        assert!(matches!(Hexagram::parse_lines("7 8 9 6 7"), Err(IChingError::InputError(_))));
+        assert!(matches!(Hexagram::parse_lines("7 8 9 6 7 7 7"), Err(IChingError::InputError(_))));
+        assert!(matches!(Hexagram::parse_lines("7 8 9 5 7 7"), Err(IChingError::InputError(_))));
+        assert!(matches!(Hexagram::parse_lines("7 8 9 x 7 7"), Err(IChingError::InputError(_))));
+        assert_eq!(Hexagram::parse_lines("7 8 9 6 7").unwrap_err().to_string(),
+                   "Invalid input: expected 6 line values, found 5");
+    }
+
+    #[test]
+    fn test_nuclear() {
+        // 3 Zhun, water over thunder: 2-3-4 are all yin, 3-4-5 make the mountain
+        let zhun = Hexagram::from_lines(Hexagram::parse_lines("7 8 8 8 7 8").unwrap());
+        assert_eq!(zhun.king_wen_number, 3);
+        assert_eq!(zhun.nuclear().king_wen_number, 23);
+
+        // the two pure hexagrams are their own nuclear hexagrams, and 63 and 64 swap
+        assert_eq!(Hexagram::from_lines([Line::StaticYang; 6]).nuclear().king_wen_number, 1);
+        assert_eq!(Hexagram::from_lines([Line::StaticYin; 6]).nuclear().king_wen_number, 2);
+        let after_completion = Hexagram::from_lines(Hexagram::parse_lines("7 8 7 8 7 8").unwrap());
+        assert_eq!(after_completion.king_wen_number, 63);
+        assert_eq!(after_completion.nuclear().king_wen_number, 64);
+
+        // moving lines don't carry over
+        let moving = Hexagram::from_lines(Hexagram::parse_lines("9 6 6 6 9 6").unwrap());
+        assert_eq!(moving.nuclear().king_wen_number, 23);
+        assert!(moving.nuclear().get_changing_lines().is_empty());
+
+        // lines 1 and 6 drop out, so there are only sixteen nuclear hexagrams
+        let nuclear: std::collections::HashSet<u8> = (0..64u8)
+            .map(|bits| Hexagram::from_trigrams((bits >> 3) as usize, (bits & 7) as usize).nuclear().king_wen_number)
+            .collect();
+        assert_eq!(nuclear.len(), 16);
+    }
+
+    #[test]
+    fn test_inverse_and_opposite() {
+        // 3 Zhun turned over is 4 Meng; every line flipped is 50 Ding
+        let zhun = Hexagram::from_lines(Hexagram::parse_lines("9 8 8 6 7 8").unwrap());
+        assert_eq!(zhun.inverse().king_wen_number, 4);
+        assert_eq!(zhun.opposite().king_wen_number, 50);
+        assert!(zhun.inverse().get_changing_lines().is_empty());
+        assert!(zhun.opposite().get_changing_lines().is_empty());
+
+        // every King Wen pair is an inverse pair, except the eight symmetric hexagrams
+        // which are paired with their opposites
+        let mut symmetric = Vec::new();
+        for bits in 0..64u8 {
+            let hexagram = Hexagram::from_trigrams((bits >> 3) as usize, (bits & 7) as usize);
+            let inverse = hexagram.inverse();
+            assert_eq!(inverse.inverse().lines, hexagram.lines);
+            assert_eq!(hexagram.opposite().opposite().lines, hexagram.lines);
+
+            if inverse.king_wen_number == hexagram.king_wen_number {
+                symmetric.push(hexagram.king_wen_number);
+                assert_eq!(hexagram.opposite().king_wen_number, hexagram.king_wen_partner());
+            } else {
+                assert_eq!(inverse.king_wen_number, hexagram.king_wen_partner());
+            }
+        }
+        symmetric.sort();
+        assert_eq!(symmetric, vec![1, 2, 27, 28, 29, 30, 61, 62]);
+    }
+
+    #[test]
+    fn test_glyphs() {
+        // the Unicode block in order, and the trigrams in TRIGRAMS order
+        let glyphs: Vec<char> = "䷀䷁䷂䷃䷄䷅䷆䷇䷈䷉䷊䷋䷌䷍䷎䷏䷐䷑䷒䷓䷔䷕䷖䷗䷘䷙䷚䷛䷜䷝䷞䷟\
+            ䷠䷡䷢䷣䷤䷥䷦䷧䷨䷩䷪䷫䷬䷭䷮䷯䷰䷱䷲䷳䷴䷵䷶䷷䷸䷹䷺䷻䷼䷽䷾䷿".chars().collect();
+        let trigrams: Vec<char> = "☷☳☵☱☶☲☴☰".chars().collect();
+        assert_eq!(glyphs.len(), 64);
+
+        for binary in 0..64 {
+            let lines = std::array::from_fn(|i| if binary & (1 << i) != 0 { Line::StaticYang } else { Line::StaticYin });
+            let hexagram = Hexagram::from_lines(lines);
+            assert_eq!(hexagram.glyph(), glyphs[hexagram.king_wen_number as usize - 1], "hexagram {}", hexagram.king_wen_number);
+
+            let upper = trigrams[binary >> 3];
+            let lower = trigrams[binary & 0b111];
+            assert!(hexagram.diagram_in(RenderStyle::Unicode).starts_with(&format!("{}  {}{}  ", glyphs[hexagram.king_wen_number as usize - 1], upper, lower)));
+
+            // and the box drawing reads back as the same lines
+            let drawn: Vec<bool> = hexagram.diagram_in(RenderStyle::Box).lines().take(6)
+                .map(|line| !line[2..].contains(' '))
+                .collect();
+            let expected: Vec<bool> = (0..6).rev().map(|i| binary & (1 << i) != 0).collect();
+            assert_eq!(drawn, expected, "hexagram {}", hexagram.king_wen_number);
+        }
+        for (trigram, glyph) in trigrams.iter().enumerate() {
+            assert_eq!(Hexagram::trigram_glyph(trigram), *glyph, "{}", Hexagram::TRIGRAMS[trigram]);
+        }
+    }
+
+    #[test]
+    fn test_diagram_styles() {
+        // 18, Mountain over Wind, with lines 2 and 4 moving
+        let hexagram = Hexagram::from_lines(Hexagram::parse_lines("8 9 7 6 8 7").unwrap());
+        assert_eq!(hexagram.diagram_in(RenderStyle::Unicode), "䷑  ☶☴  Mountain over Wind\nchanging lines: 2, 4\n");
+        assert_eq!(hexagram.diagram_in(RenderStyle::Box),
+            "6 ━━━━━━━━━━━\n5 ━━━━   ━━━━\n4 ━━━━ × ━━━━\n3 ━━━━━━━━━━━\n2 ━━━━━○━━━━━\n1 ━━━━   ━━━━\n☶ over ☴  Mountain over Wind\n");
+        assert_eq!(hexagram.diagram_in(RenderStyle::Ascii), hexagram.diagram());
+        assert_eq!(RenderStyle::from_arg("box"), Some(RenderStyle::Box));
+        assert_eq!(RenderStyle::from_arg("fancy"), None);
+    }
+
+    #[test]
+    fn test_hexagram_display() {
+        let hexagram = Hexagram::from_lines([Line::StaticYang; 6]);
+        assert_eq!(hexagram.to_string(),
+            "HEXAGRAM 1\n6 ---------\n5 ---------\n4 ---------\n3 ---------\n2 ---------\n1 ---------\nHeaven over Heaven\n");
+    }
+
+    #[test]
+    fn test_hexagram_calculate_number() {
+        // Test Hexagram 1 (Qian - all yang lines)
+        let all_yang = [Line::StaticYang; 6];
+        assert_eq!(Hexagram::calculate_number(&all_yang), 1);
+        // Test Hexagram 2 (Kun - all yin lines)
+        let all_yin = [Line::StaticYin; 6];
+        assert_eq!(Hexagram::calculate_number(&all_yin), 2);
+        // that's probably good enough lmao
+    }
+
+    #[test]
+    fn test_hexagram_change_with_changing_lines() {
+        let lines = [
+            Line::StaticYin,      // stays yin
+            Line::ChangingYang,   // becomes yin
+            Line::StaticYang,     // stays yang
+            Line::ChangingYin,    // becomes yang
+            Line::StaticYin,      // stays yin
+            Line::StaticYang,     // stays yang
+        ];
+        let hexagram = Hexagram {
+            lines,
+            king_wen_number: 1,
+        };
+        
+        let future = hexagram.change().unwrap();
+        assert!(matches!(future.lines[1], Line::StaticYin));
+        assert!(matches!(future.lines[3], Line::StaticYang));
+    }
+
+    #[test]
+    fn test_hexagram_change_no_changing_lines() {
+        let lines = [Line::StaticYin, Line::StaticYang, Line::StaticYin, 
+                     Line::StaticYang, Line::StaticYin, Line::StaticYang];
+        let hexagram = Hexagram {
+            lines,
+            king_wen_number: 1,
+        };
+        
+        assert!(hexagram.change().is_none());
+    }
+
+    #[test]
+    fn test_calculate_trigram() {
+        // Test Earth trigram (000)
+        let earth_lines = [Line::StaticYin, Line::StaticYin, Line::StaticYin];
+        assert_eq!(Hexagram::calculate_trigram(&earth_lines), 0);
+        
+        // Test Heaven trigram (111) 
+        let heaven_lines = [Line::StaticYang, Line::StaticYang, Line::StaticYang];
+        assert_eq!(Hexagram::calculate_trigram(&heaven_lines), 7);
+    }
+}
@@ -0,0 +1,160 @@
+//! Which texts to read: every changing line, or the traditional rules of Zhu Xi
+//! that single out the text that matters most for the number of moving lines
+
+use serde::{Deserialize, Serialize};
+
+use crate::hexagram::Hexagram;
+use crate::translation::TextLookup;
+
+/// A text from the translation, by hexagram number
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Text {
+    /// The judgement of a hexagram
+    Judgement(u8),
+    /// The image of a hexagram
+    Image(u8),
+    /// King Wen number and line position, 1 to 6 from the bottom
+    Line(u8, u8),
+    /// The "use nines" or "use sixes" text of the Creative or the Receptive
+    AllLinesChanging(u8),
+}
+
+impl Text {
+    /// Where the text is from, e.g. "hexagram 18, line 4"
+    pub fn label(&self) -> String {
+        match self {
+            Text::Judgement(number) => format!("hexagram {}, the judgement", number),
+            Text::Image(number) => format!("hexagram {}, the image", number),
+            Text::Line(number, position) => format!("hexagram {}, line {}", number, position),
+            Text::AllLinesChanging(number) => format!("hexagram {}, all lines changing", number),
+        }
+    }
+
+    /// The King Wen number of the hexagram it's from
+    pub fn hexagram(&self) -> u8 {
+        match self {
+            Text::Judgement(number) | Text::Image(number) | Text::Line(number, _) | Text::AllLinesChanging(number) => *number,
+        }
+    }
+
+    /// The words themselves, and the translation they're from
+    pub fn text<'a>(&self, texts: &TextLookup<'a>) -> Option<(&'a str, &'a str)> {
+        match self {
+            Text::Judgement(number) => texts.judgement(*number),
+            Text::Image(number) => texts.image(*number),
+            Text::Line(number, position) => texts.line(*number, *position),
+            // translations without the seventh text fall back on the judgement
+            Text::AllLinesChanging(number) if !texts.has_all_lines_changing(*number) => texts.judgement(*number),
+            Text::AllLinesChanging(number) => texts.all_lines_changing(*number),
+        }
+    }
+}
+
+/// How to decide which text answers the question. In JSON it goes by its command line name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum InterpretationRule {
+    /// Read every changing line; nothing is singled out
+    #[default]
+    #[serde(rename = "all")]
+    AllLines,
+    /// Zhu Xi's rules, from the number of changing lines:
+    /// 0, the present judgement; 1, that line; 2, the upper of the two;
+    /// 3, the judgements of both hexagrams; 4, the two unchanging lines of the future hexagram;
+    /// 5, its one unchanging line; 6, the future judgement
+    /// (or the "use nines" and "use sixes" texts for the Creative and the Receptive)
+    #[serde(rename = "zhu-xi")]
+    ZhuXi,
+}
+
+impl InterpretationRule {
+    /// The texts to read first, in the order they should be read
+    pub fn primary_texts(&self, present: &Hexagram, future: Option<&Hexagram>) -> Vec<Text> {
+        if *self == InterpretationRule::AllLines {
+            return Vec::new();
+        }
+
+        let changing = present.get_changing_lines();
+        let future = match future {
+            Some(future) => future,
+            None => return vec![Text::Judgement(present.king_wen_number)],
+        };
+        // the lines of the future hexagram that weren't moving in the present one
+        let unchanged: Vec<u8> = (1..=6).filter(|line| !changing.contains(line)).collect();
+
+        match changing.len() {
+            1 => vec![Text::Line(present.king_wen_number, changing[0])],
+            2 => vec![Text::Line(present.king_wen_number, changing[1])],
+            3 => vec![Text::Judgement(present.king_wen_number), Text::Judgement(future.king_wen_number)],
+            4 | 5 => unchanged.iter().map(|line| Text::Line(future.king_wen_number, *line)).collect(),
+            // the Creative and the Receptive keep their own text when every line moves
+            _ if matches!(present.king_wen_number, 1 | 2) => vec![Text::AllLinesChanging(present.king_wen_number)],
+            _ => vec![Text::Judgement(future.king_wen_number)],
+        }
+    }
+
+    /// Why those texts, in a few words
+    pub fn explain(&self, present: &Hexagram) -> &'static str {
+        let changing = present.get_changing_lines().len();
+        match (self, changing) {
+            (InterpretationRule::AllLines, _) => "every changing line counts",
+            (InterpretationRule::ZhuXi, 0) => "no lines are changing, so read the judgement",
+            (InterpretationRule::ZhuXi, 1) => "one line is changing, so read that line",
+            (InterpretationRule::ZhuXi, 2) => "two lines are changing, so the upper one leads",
+            (InterpretationRule::ZhuXi, 3) => "three lines are changing, so read both judgements",
+            (InterpretationRule::ZhuXi, 4) => "four lines are changing, so read the two still lines of the future hexagram",
+            (InterpretationRule::ZhuXi, 5) => "five lines are changing, so read the still line of the future hexagram",
+            (InterpretationRule::ZhuXi, _) if matches!(present.king_wen_number, 1 | 2) =>
+                "every line is changing, so read the seventh text of the Creative or the Receptive",
+            (InterpretationRule::ZhuXi, _) => "every line is changing, so read the future judgement",
+        }
+    }
+
+    /// The rule from its command line name
+    pub fn from_arg(arg: &str) -> Option<Self> {
+        match arg {
+            "all" => Some(InterpretationRule::AllLines),
+            "zhu-xi" => Some(InterpretationRule::ZhuXi),
+            _ => None
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn primary(lines: &str) -> Vec<Text> {
+        let present = Hexagram::from_lines(Hexagram::parse_lines(lines).unwrap());
+        InterpretationRule::ZhuXi.primary_texts(&present, present.change().as_ref())
+    }
+
+    #[test]
+    fn test_zhu_xi_rules() {
+        // 18 Gu is 8 7 7 8 8 7 from the bottom
+        assert_eq!(primary("8 7 7 8 8 7"), vec![Text::Judgement(18)]);
+        assert_eq!(primary("8 9 7 8 8 7"), vec![Text::Line(18, 2)]);
+        assert_eq!(primary("8 9 7 6 8 7"), vec![Text::Line(18, 4)]);
+        // lines 1, 2 and 3 change: 18 becomes 27 Yi
+        assert_eq!(primary("6 9 9 8 8 7"), vec![Text::Judgement(18), Text::Judgement(27)]);
+        // four moving leaves lines 5 and 6 of the future hexagram
+        assert_eq!(primary("6 9 9 6 8 7"), vec![Text::Line(21, 5), Text::Line(21, 6)]);
+        assert_eq!(primary("6 9 9 6 6 7"), vec![Text::Line(25, 6)]);
+        assert_eq!(primary("6 9 9 6 6 9"), vec![Text::Judgement(17)]);
+
+        // except the Creative and the Receptive
+        assert_eq!(primary("9 9 9 9 9 9"), vec![Text::AllLinesChanging(1)]);
+        assert_eq!(primary("6 6 6 6 6 6"), vec![Text::AllLinesChanging(2)]);
+
+        // every changing line counts the same with AllLines
+        let present = Hexagram::from_lines(Hexagram::parse_lines("8 9 7 6 8 7").unwrap());
+        assert!(InterpretationRule::AllLines.primary_texts(&present, present.change().as_ref()).is_empty());
+    }
+
+    #[test]
+    fn test_from_arg() {
+        assert_eq!(InterpretationRule::from_arg("zhu-xi"), Some(InterpretationRule::ZhuXi));
+        assert_eq!(InterpretationRule::from_arg("all"), Some(InterpretationRule::AllLines));
+        assert_eq!(InterpretationRule::from_arg("wilhelm"), None);
+    }
+}
@@ -0,0 +1,42 @@
+//! Random I Ching divinations.
+//!
+//! A [`Divination`] is cast with one of the [`CastingMethod`]s, which gives a present
+//! [`Hexagram`] and, if any of its [`Line`]s are moving, a future one. The texts come from a
+//! translation loaded with [`load_hexagram_data`], or the one compiled into the crate.
+//!
+//! ```
+//! use rs_iching::{CastingMethod, Divination};
+//! use rs_iching::translation::embedded_iching_data;
+//!
+//! let translation = embedded_iching_data()?.into_translation();
+//! let reading = Divination::with_method(("Wilhelm/Baynes", &translation), CastingMethod::YarrowStalks);
+//! println!("{}", reading);
+//! # Ok::<(), rs_iching::error::IChingError>(())
+//! ```
+
+#![warn(missing_docs)]
+
+pub mod calendar;
+pub mod casting;
+pub mod divination;
+pub mod entropy;
+pub mod error;
+pub mod hexagram;
+pub mod interpretation;
+pub mod line;
+pub mod overlay;
+pub mod reading;
+pub mod registry;
+pub mod render;
+pub mod svg;
+mod tables;
+pub mod translation;
+mod translation_file;
+pub mod validation;
+
+pub use casting::{CastingMethod, LineDistribution};
+pub use divination::Divination;
+pub use error::IChingError;
+pub use hexagram::Hexagram;
+pub use line::Line;
+pub use translation::{load_hexagram_data, IChingTranslation};
@@ -0,0 +1,346 @@
+//! The six kinds of line and the ways of casting one
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::casting::{LineDistribution, YarrowChange, YarrowLineRecord, YARROW_STALKS};
+use crate::error::IChingError;
+
+/// A broken line is "yin" and a solid line is "yang"; lines may be either static (young) or moving (old).
+/// In JSON a line is its number, 6 to 9.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "u8", try_from = "u8")]
+pub enum Line {
+    /// 6, old yin, which changes to yang
+    ChangingYin,
+    /// 7, young yang
+    StaticYang,
+    /// 8, young yin
+    StaticYin,
+    /// 9, old yang, which changes to yin
+    ChangingYang,
+}
+
+/// Whether a line is broken or solid, moving or not
+#[derive(Debug)]
+pub enum Aspect {
+    /// Broken
+    Yin,
+    /// Solid
+    Yang
+}
+
+impl Line {
+    /// The three coin method
+    pub fn generate(rng: &mut impl rand::Rng) -> Self {
+        //flip three coins. heads are worth 3 and tails 2. sum the numbers
+
+        // there's some fancy syntax here that I should get used to
+
+        /* 
+        // first I did it the readable way, like this:
+
+        let mut sum = 0;
+        for _ in 0..3 {
+            let cointoss = rng.random_bool(0.5);
+
+            if cointoss == true {
+                sum += 3;
+            } else {
+                sum += 2;
+            }
+        }
+        */
+
+        // fancy syntax version. I guess it's a one-liner?
+        let sum = (0..3).map(|_| {if rng.random_bool(0.5) {3} else {2}}).sum();
+
+        match sum {
+            6 => Line::ChangingYin,
+            7 => Line::StaticYang,
+            8 => Line::StaticYin,
+            9 => Line::ChangingYang,
+            _ => unreachable!()
+        }
+    }
+
+    /// Yarrow-stalk probabilities without simulating the stalks: 
+    /// old yin 1/16, young yang 5/16, young yin 7/16, old yang 3/16.
+    pub fn generate_yarrow(rng: &mut impl rand::Rng) -> Self {
+        Line::sample(&LineDistribution::YARROW, rng)
+    }
+
+    /// Draw a line with the given probabilities for 6, 7, 8 and 9
+    pub fn sample(distribution: &LineDistribution, rng: &mut impl rand::Rng) -> Self {
+        let x: f64 = rng.random();
+        let mut cumulative = 0.0;
+        for (probability, line) in distribution.probabilities.iter().zip(LineDistribution::LINES) {
+            cumulative += probability;
+            if x < cumulative {
+                return line;
+            }
+        }
+        // only reachable through rounding, when x is within a hair of 1
+        LineDistribution::LINES.into_iter()
+            .zip(distribution.probabilities)
+            .rfind(|(_, probability)| *probability > 0.0)
+            .map(|(line, _)| line)
+            .unwrap()
+    }
+
+    /// Actually count out the stalks. Of the 50 stalks one is set aside and never used;
+    /// the other 49 go through three changes, each of which divides the bundle in two,
+    /// hangs one stalk between the fingers, and counts both heaps off by fours.
+    /// Whatever is left over at the end divided by four gives the line value.
+    pub fn generate_yarrow_simulated(rng: &mut impl rand::Rng) -> Self {
+        Line::generate_yarrow_recorded(rng).line
+    }
+
+    /// Same as generate_yarrow_simulated, but keeps every heap size along the way
+    pub fn generate_yarrow_recorded(rng: &mut impl rand::Rng) -> YarrowLineRecord {
+        let mut stalks: u32 = YARROW_STALKS - 1;
+        let changes: [YarrowChange; 3] = std::array::from_fn(|_| {
+            let change = Line::yarrow_change(stalks, rng);
+            stalks -= change.set_aside();
+            change
+        });
+
+        let line = match stalks / 4 {
+            6 => Line::ChangingYin,
+            7 => Line::StaticYang,
+            8 => Line::StaticYin,
+            9 => Line::ChangingYang,
+            _ => unreachable!()
+        };
+
+        YarrowLineRecord { changes, remaining: stalks, line }
+    }
+
+    /// One change of the yarrow stalk ritual.
+    pub fn yarrow_change(stalks: u32, rng: &mut impl rand::Rng) -> YarrowChange {
+        // divide into two heaps, each with at least one stalk
+        let left = rng.random_range(1..stalks);
+        // take one stalk from the right heap and hang it between the fingers
+        let right = stalks - left - 1;
+
+        // count off by fours; a remainder of zero counts as four
+        let left_remainder = match left % 4 { 0 => 4, r => r };
+        let right_remainder = match right % 4 {
+            0 if right == 0 => 0,
+            0 => 4,
+            r => r
+        };
+
+        YarrowChange { stalks, left, right, left_remainder, right_remainder }
+    }
+
+    /// The line for a thrown total of 6, 7, 8 or 9
+    pub fn from_value(value: u8) -> Result<Self, IChingError> {
+        match value {
+            6 => Ok(Line::ChangingYin),
+            7 => Ok(Line::StaticYang),
+            8 => Ok(Line::StaticYin),
+            9 => Ok(Line::ChangingYang),
+            _ => Err(IChingError::InputError(format!("line value {} is not 6, 7, 8 or 9", value)))
+        }
+    }
+
+    /// The number the line is named for: 6, 7, 8 or 9
+    pub fn value(&self) -> u8 {
+        match self {
+            Line::ChangingYin => 6,
+            Line::StaticYang => 7,
+            Line::StaticYin => 8,
+            Line::ChangingYang => 9,
+        }
+    }
+
+    /// The line drawn wide with box-drawing characters, for terminals that have them
+    pub fn wide(&self) -> &'static str {
+        match self {
+            Line::StaticYang   => "━━━━━━━━━━━",
+            Line::StaticYin    => "━━━━   ━━━━",
+            Line::ChangingYang => "━━━━━○━━━━━",
+            Line::ChangingYin  => "━━━━ × ━━━━",
+        }
+    }
+
+    /// "old yin", "young yang" and so on
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Line::ChangingYin => "old yin",
+            Line::StaticYang => "young yang",
+            Line::StaticYin => "young yin",
+            Line::ChangingYang => "old yang",
+        }
+    }
+
+    /// Whether it's an old line, which changes in the future hexagram
+    pub fn is_changing(&self) -> bool {
+        matches!(self, Line::ChangingYang | Line::ChangingYin)
+    }
+
+    /// The line in the future hexagram: old lines become young lines of the other aspect
+    pub fn change_line(&self) -> Line {
+        match self {
+            Line::ChangingYang => Line::StaticYin,
+            Line::ChangingYin  => Line::StaticYang,
+            other => *other
+        }
+    }
+
+    /// The young line of the same aspect: moving lines come to rest without changing
+    pub fn to_static(&self) -> Line {
+        match self {
+            Line::ChangingYang | Line::StaticYang => Line::StaticYang,
+            Line::ChangingYin | Line::StaticYin => Line::StaticYin,
+        }
+    }
+
+    /// The young line of the other aspect
+    pub fn opposite(&self) -> Line {
+        match self {
+            Line::ChangingYang | Line::StaticYang => Line::StaticYin,
+            Line::ChangingYin | Line::StaticYin => Line::StaticYang,
+        }
+    }
+
+    /// Yin or yang
+    pub fn get_aspect(&self) -> Aspect {
+        match self {
+            Line::ChangingYang => Aspect::Yang,
+            Line::StaticYang => Aspect::Yang,
+            Line::ChangingYin => Aspect::Yin,
+            Line::StaticYin => Aspect::Yin
+        }
+    }
+
+}
+
+impl From<Line> for u8 {
+    fn from(line: Line) -> u8 {
+        line.value()
+    }
+}
+
+impl TryFrom<u8> for Line {
+    type Error = IChingError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Line::from_value(value)
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Line::StaticYang   => write!(f, "---------"),
+            Line::StaticYin    => write!(f, "---   ---"),
+            Line::ChangingYang => write!(f, "----o----"),
+            Line::ChangingYin  => write!(f, "--- x ---")
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_generation_known_values() {
+        use rand::SeedableRng;
+        use rand::rngs::StdRng;
+        
+        let mut rng = StdRng::seed_from_u64(42);
+        
+        // Test with known seed - these are the actual results from seed 42
+        let line1 = Line::generate(&mut rng);
+        let line2 = Line::generate(&mut rng);
+        let line3 = Line::generate(&mut rng);
+        
+        assert!(matches!(line1, Line::ChangingYin));
+        assert!(matches!(line2, Line::ChangingYang));
+        assert!(matches!(line3, Line::StaticYang));
+    }
+
+    #[test]
+    fn test_line_change() {
+        assert!(matches!(Line::ChangingYin.change_line(), Line::StaticYang));
+        assert!(matches!(Line::ChangingYang.change_line(), Line::StaticYin));
+        assert!(matches!(Line::StaticYin.change_line(), Line::StaticYin));
+        assert!(matches!(Line::StaticYang.change_line(), Line::StaticYang));
+    }
+
+    #[test]
+    fn test_line_to_static_and_opposite() {
+        assert_eq!(Line::ChangingYang.to_static(), Line::StaticYang);
+        assert_eq!(Line::ChangingYin.to_static(), Line::StaticYin);
+        assert_eq!(Line::StaticYin.to_static(), Line::StaticYin);
+        assert_eq!(Line::ChangingYang.opposite(), Line::StaticYin);
+        assert_eq!(Line::StaticYin.opposite(), Line::StaticYang);
+    }
+
+    #[test]
+    fn test_line_get_aspect() {
+        assert!(matches!(Line::ChangingYang.get_aspect(), Aspect::Yang));
+        assert!(matches!(Line::StaticYang.get_aspect(), Aspect::Yang));
+        assert!(matches!(Line::ChangingYin.get_aspect(), Aspect::Yin));
+        assert!(matches!(Line::StaticYin.get_aspect(), Aspect::Yin));
+    }
+
+    #[test]
+    fn test_line_display() {
+        assert_eq!(Line::StaticYang.to_string(), "---------");
+        assert_eq!(Line::StaticYin.to_string(), "---   ---");
+        assert_eq!(Line::ChangingYang.to_string(), "----o----");
+        assert_eq!(Line::ChangingYin.to_string(), "--- x ---");
+    }
+
+    #[test]
+    fn test_yarrow_probabilities() {
+        use rand::SeedableRng;
+        use rand::rngs::StdRng;
+
+        // count how often each of 6, 7, 8, 9 comes up
+        fn tally(generate: fn(&mut StdRng) -> Line) -> [f64; 4] {
+            let mut rng = StdRng::seed_from_u64(7);
+            let mut counts = [0u32; 4];
+            for _ in 0..16000 {
+                let index = match generate(&mut rng) {
+                    Line::ChangingYin => 0,
+                    Line::StaticYang => 1,
+                    Line::StaticYin => 2,
+                    Line::ChangingYang => 3,
+                };
+                counts[index] += 1;
+            }
+            counts.map(|c| c as f64 / 16000.0)
+        }
+
+        let expected = [1.0 / 16.0, 5.0 / 16.0, 7.0 / 16.0, 3.0 / 16.0];
+        // the simulation isn't exact because the heaps are split uniformly, but it's close
+        for (generate, tolerance) in [(Line::generate_yarrow as fn(&mut StdRng) -> Line, 0.01),
+                                      (Line::generate_yarrow_simulated, 0.04)] {
+            let frequencies = tally(generate);
+            for (freq, exp) in frequencies.iter().zip(expected) {
+                assert!((freq - exp).abs() < tolerance, "{:?} vs {:?}", frequencies, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_yarrow_change_sets_aside() {
+        use rand::SeedableRng;
+        use rand::rngs::StdRng;
+
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..100 {
+            // first change sets aside 5 or 9, later ones 4 or 8
+            assert!(matches!(Line::yarrow_change(49, &mut rng).set_aside(), 5 | 9));
+            assert!(matches!(Line::yarrow_change(44, &mut rng).set_aside(), 4 | 8));
+            assert!(matches!(Line::yarrow_change(40, &mut rng).set_aside(), 4 | 8));
+        }
+    }
+}
@@ -0,0 +1,241 @@
+//! Partial translations laid over a complete one. An overlay file looks like a translation file
+//! but can leave out any hexagram and any field, and names the translation it fills in with
+//! `metadata.overlays`. Whatever it does have replaces the base translation field by field,
+//! and every replaced field is remembered so we can say which layer it came from.
+
+use std::collections::BTreeMap;
+use std::fs;
+
+use serde_json::Value;
+
+use crate::error::IChingError;
+use crate::translation::{HexagramData, IChingData, IChingTranslation};
+use crate::validation::{self, ValidationProblem};
+
+/// A partial translation file, checked against the schema with nothing required
+#[derive(Debug, Clone)]
+pub struct Overlay {
+    /// Its translation_source
+    pub name: String,
+    /// The translation_source of the translation it goes over, if it says
+    pub base: Option<String>,
+    json: Value,
+}
+
+impl Overlay {
+    /// Load an overlay file. Without a translation_source it goes by the file name.
+    pub fn load(filename: &str) -> Result<Self, IChingError> {
+        let json: Value = serde_json::from_str(&fs::read_to_string(filename)?)?;
+        let stem = std::path::Path::new(filename).file_stem().unwrap_or_default().to_string_lossy();
+        Overlay::from_json(json, &stem)
+    }
+
+    /// An overlay from JSON that's already been parsed
+    pub fn from_json(json: Value, fallback_name: &str) -> Result<Self, IChingError> {
+        let problems = validation::overlay_schema_problems(&json);
+        if !problems.is_empty() {
+            return Err(IChingError::ValidationError(problems));
+        }
+
+        let metadata = &json["metadata"];
+        Ok(Overlay {
+            name: metadata["translation_source"].as_str().unwrap_or(fallback_name).to_string(),
+            base: metadata["overlays"].as_str().map(str::to_string),
+            json,
+        })
+    }
+
+    /// Whether a parsed file is an overlay rather than a whole translation
+    pub fn is_overlay(json: &Value) -> bool {
+        json["metadata"]["overlays"].is_string()
+    }
+}
+
+/// A translation built from a base and any number of overlays
+#[derive(Debug, Clone)]
+pub struct LayeredTranslation {
+    /// The base with every overlay laid over it
+    pub translation: IChingTranslation,
+    /// Where each field came from
+    pub provenance: Provenance,
+}
+
+/// Which layer each field came from. Fields that aren't listed came from the base.
+#[derive(Debug, Clone, Default)]
+pub struct Provenance {
+    /// The name of the translation the overlays were laid over
+    pub base: String,
+    /// Field paths like "18/judgement" or "18/lines/4/text" to the layer they came from
+    pub fields: BTreeMap<String, String>,
+}
+
+impl Provenance {
+    /// The layer a field came from
+    pub fn source_of(&self, field: &str) -> &str {
+        self.fields.get(field).unwrap_or(&self.base)
+    }
+
+    /// One line per layer with the fields it provided
+    pub fn report(&self) -> String {
+        let mut by_layer: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for (field, layer) in &self.fields {
+            by_layer.entry(layer).or_default().push(field);
+        }
+
+        let mut result = format!("{}: everything else\n", self.base);
+        for (layer, fields) in by_layer {
+            result.push_str(&format!("{}: {} field{} ({})\n",
+                layer, fields.len(), if fields.len() == 1 { "" } else { "s" }, fields.join(", ")));
+        }
+        result
+    }
+}
+
+impl LayeredTranslation {
+    /// Lay the overlays over the base translation, in order, each one winning over those before it.
+    /// The result is checked the same way a whole translation is.
+    pub fn new(base_name: &str, base: &IChingTranslation, overlays: &[Overlay]) -> Result<Self, IChingError> {
+        let mut provenance = Provenance { base: base_name.to_string(), ..Default::default() };
+        let mut hexagrams: BTreeMap<u8, Value> = base.iter()
+            .map(|(number, data)| (*number, serde_json::to_value(data).expect("hexagram data is always valid JSON")))
+            .collect();
+
+        for overlay in overlays {
+            for hexagram in overlay.json["hexagrams"].as_array().into_iter().flatten() {
+                let number = hexagram["king_wen_number"].as_u64().unwrap_or_default() as u8;
+                let Some(base) = hexagrams.get_mut(&number) else {
+                    return Err(IChingError::ValidationError(vec![ValidationProblem {
+                        hexagram: Some(number),
+                        path: "/hexagrams".to_string(),
+                        message: format!("{} has no hexagram {} to lay {} over", base_name, number, overlay.name),
+                    }]));
+                };
+                merge(base, hexagram, &number.to_string(), &overlay.name, &mut provenance);
+            }
+        }
+
+        let hexagrams = hexagrams.into_values()
+            .map(serde_json::from_value::<HexagramData>)
+            .collect::<Result<Vec<_>, _>>()?;
+        let data = IChingData { hexagrams, metadata: None };
+        let problems = validation::semantic_problems(&data);
+        if !problems.is_empty() {
+            return Err(IChingError::ValidationError(problems));
+        }
+
+        Ok(LayeredTranslation { translation: data.into_translation(), provenance })
+    }
+}
+
+/// Merge one JSON value into another, field by field. Lines are matched up by position;
+/// everything else that isn't an object (keywords included) is replaced whole.
+fn merge(base: &mut Value, overlay: &Value, path: &str, layer: &str, provenance: &mut Provenance) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                // the numbers are how the hexagram and line were found, not something the overlay provides
+                if key == "king_wen_number" || key == "position" {
+                    continue;
+                }
+                let field = format!("{}/{}", path, key);
+                if key == "lines" && let (Some(base_lines), Some(lines)) = (base.get_mut(key).and_then(Value::as_array_mut), value.as_array()) {
+                    for line in lines {
+                        let position = line["position"].as_u64().unwrap_or_default();
+                        let line_path = format!("{}/{}", field, position);
+                        match base_lines.iter_mut().find(|base_line| base_line["position"].as_u64() == Some(position)) {
+                            Some(base_line) => merge(base_line, line, &line_path, layer, provenance),
+                            None => {
+                                base_lines.push(line.clone());
+                                provenance.fields.insert(line_path, layer.to_string());
+                            }
+                        }
+                    }
+                } else {
+                    merge(base.entry(key.as_str()).or_insert(Value::Null), value, &field, layer, provenance);
+                }
+            }
+        }
+        (base, overlay) => {
+            *base = overlay.clone();
+            provenance.fields.insert(path.to_string(), layer.to_string());
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translation::load_hexagram_data;
+
+    fn overlay(json: &str) -> Overlay {
+        Overlay::from_json(serde_json::from_str(json).unwrap(), "test").unwrap()
+    }
+
+    #[test]
+    fn test_overlay_field_by_field() {
+        let base = load_hexagram_data("data/wilhelm_baynes.json").unwrap();
+        let judgements = overlay(r#"{
+            "metadata": {"translation_source": "Judgements", "overlays": "Wilhelm/Baynes"},
+            "hexagrams": [
+                {"king_wen_number": 18, "judgement": "Decay. Supreme success.", "name": {"pinyin": "Gu"}}
+            ]
+        }"#);
+        let lines = overlay(r#"{
+            "metadata": {"overlays": "Wilhelm/Baynes"},
+            "hexagrams": [
+                {"king_wen_number": 18, "lines": [{"position": 4, "text": "Tolerating the decay of the father."}]},
+                {"king_wen_number": 1, "judgement": "Sublime success."}
+            ]
+        }"#);
+        assert_eq!(judgements.name, "Judgements");
+        assert_eq!(judgements.base.as_deref(), Some("Wilhelm/Baynes"));
+        assert_eq!(lines.name, "test");
+
+        let layered = LayeredTranslation::new("Wilhelm/Baynes", &base, &[judgements, lines]).unwrap();
+        let gu = &layered.translation[&18];
+        assert_eq!(gu.judgement, "Decay. Supreme success.");
+        assert_eq!(gu.name.pinyin.as_deref(), Some("Gu"));
+        // the rest of the name, and the rest of the lines, are still the base's
+        assert_eq!(gu.name.english, base[&18].name.english);
+        assert_eq!(gu.lines[3].text, "Tolerating the decay of the father.");
+        assert_eq!(gu.lines[3].line_type, base[&18].lines[3].line_type);
+        assert_eq!(gu.lines[2].text, base[&18].lines[2].text);
+        assert_eq!(layered.translation[&1].judgement, "Sublime success.");
+        assert_eq!(layered.translation.len(), 64);
+
+        let provenance = &layered.provenance;
+        assert_eq!(provenance.source_of("18/judgement"), "Judgements");
+        assert_eq!(provenance.source_of("18/lines/4/text"), "test");
+        assert_eq!(provenance.source_of("18/image"), "Wilhelm/Baynes");
+        assert_eq!(provenance.report(), "Wilhelm/Baynes: everything else\n\
+            Judgements: 2 fields (18/judgement, 18/name/pinyin)\n\
+            test: 2 fields (1/judgement, 18/lines/4/text)\n");
+    }
+
+    #[test]
+    fn test_later_overlays_win() {
+        let base = load_hexagram_data("data/wilhelm_baynes.json").unwrap();
+        let first = overlay(r#"{"hexagrams": [{"king_wen_number": 2, "image": "first"}]}"#);
+        let second = overlay(r#"{"metadata": {"translation_source": "second"}, "hexagrams": [{"king_wen_number": 2, "image": "second"}]}"#);
+
+        let layered = LayeredTranslation::new("base", &base, &[first, second]).unwrap();
+        assert_eq!(layered.translation[&2].image, "second");
+        assert_eq!(layered.provenance.source_of("2/image"), "second");
+    }
+
+    #[test]
+    fn test_overlays_are_checked() {
+        // nothing is required, but what's there still has to fit the schema
+        let error = Overlay::from_json(serde_json::json!({"hexagrams": [{"king_wen_number": 65}]}), "bad").unwrap_err();
+        assert!(error.to_string().contains("hexagram 65 at /hexagrams/0/king_wen_number"), "{}", error);
+        let error = Overlay::from_json(serde_json::json!({"hexagrams": [{"judgement": "no number"}]}), "bad").unwrap_err();
+        assert!(error.to_string().contains("king_wen_number"), "{}", error);
+
+        // and the merged translation has to make sense
+        let base = load_hexagram_data("data/wilhelm_baynes.json").unwrap();
+        let wrong = overlay(r#"{"hexagrams": [{"king_wen_number": 1, "lines": [{"position": 1, "type": "yin"}]}]}"#);
+        let error = LayeredTranslation::new("base", &base, &[wrong]).unwrap_err();
+        assert!(error.to_string().contains("hexagram 1 at /hexagrams/0/lines/0/type: is yin but line 1 is yang"), "{}", error);
+    }
+}
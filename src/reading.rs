@@ -0,0 +1,392 @@
+//! Readings as JSON, so they can be piped into other tools. The format is published as
+//! data/reading_schema.json, and `format_version` goes up whenever a field is removed
+//! or changes meaning.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::calendar::{ChineseTime, LunarDate};
+use crate::casting::{CastingMethod, LineDistribution};
+use crate::divination::Divination;
+use crate::error::IChingError;
+use crate::hexagram::Hexagram;
+use crate::interpretation::{InterpretationRule, Text};
+use crate::line::Line;
+use crate::translation::{NamedTranslation, TextLookup};
+
+/// The schema readings are written to
+pub const READING_SCHEMA: &str = include_str!("../data/reading_schema.json");
+
+/// The format_version of the readings this version writes
+pub const READING_FORMAT_VERSION: u32 = 1;
+
+/// Everything about a reading, with its texts looked up
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reading {
+    /// READING_FORMAT_VERSION when it was written
+    pub format_version: u32,
+    /// When it was cast, RFC 3339
+    pub timestamp: String,
+    /// What the querent asked, if they typed it in
+    pub question: Option<String>,
+    /// How the lines were cast
+    pub method: MethodRecord,
+    /// Written as a string, since JSON numbers above 2^53 don't survive most parsers
+    #[serde(serialize_with = "seed_to_string", deserialize_with = "seed_from_string")]
+    pub seed: Option<u64>,
+    /// The translation the texts are from
+    pub translation: String,
+    /// Which rule picked out read_first
+    pub interpretation: InterpretationRule,
+    /// The hexagram as it was cast
+    pub present: Hexagram,
+    /// What it changes into, if any lines are moving
+    pub future: Option<Hexagram>,
+    /// What the interpretation rule says to read first, see InterpretationRule::primary_texts
+    pub read_first: Vec<SelectedText>,
+    /// The present judgement and image, the changing lines, and the future judgement and image
+    pub texts: Vec<SelectedText>,
+}
+
+/// How the lines were cast, with whatever the method needs to be told apart from the others.
+/// Lines entered by hand are the present hexagram's lines, so they aren't repeated here.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MethodRecord {
+    /// The option that picks the method: "coins", "yarrow" or "yarrow-sim" for --method,
+    /// or "distribution", "numbers", "time" or "lines"
+    pub name: String,
+    /// e.g. "three coins"
+    pub description: String,
+    /// For distribution, the probabilities of 6, 7, 8 and 9
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probabilities: Option<[f64; 4]>,
+    /// For numbers, the two numbers, as strings like the seed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub numbers: Option<[String; 2]>,
+    /// For time, the moment in the Chinese calendar
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<TimeRecord>,
+}
+
+/// A ChineseTime as it's written in a reading
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeRecord {
+    /// The Gregorian year the lunar year began in
+    pub year: i32,
+    /// The lunar month, 1 to 12
+    pub month: u8,
+    /// Whether it's the leap month repeating `month`
+    pub leap_month: bool,
+    /// The lunar day, 1 to 30
+    pub day: u8,
+    /// 0 for Zi (23:00 to 01:00) to 11 for Hai
+    pub hour_branch: u8,
+}
+
+impl MethodRecord {
+    /// The record of a method
+    pub fn new(method: &CastingMethod) -> Self {
+        let mut record = MethodRecord {
+            name: String::new(),
+            description: method.name(),
+            probabilities: None,
+            numbers: None,
+            time: None,
+        };
+        record.name = match method {
+            CastingMethod::ThreeCoins | CastingMethod::YarrowStalks | CastingMethod::YarrowStalksSimulated =>
+                method.arg_name().expect("these all have a --method name").to_string(),
+            CastingMethod::Distribution(distribution) => {
+                record.probabilities = Some(distribution.probabilities);
+                "distribution".to_string()
+            }
+            CastingMethod::PlumBlossom { first, second } => {
+                record.numbers = Some([first.to_string(), second.to_string()]);
+                "numbers".to_string()
+            }
+            CastingMethod::PlumBlossomTime(time) => {
+                record.time = Some(TimeRecord {
+                    year: time.date.year,
+                    month: time.date.month,
+                    leap_month: time.date.leap_month,
+                    day: time.date.day,
+                    hour_branch: time.hour_branch as u8,
+                });
+                "time".to_string()
+            }
+            CastingMethod::Manual(_) => "lines".to_string(),
+        };
+        record
+    }
+
+    /// The method again. Lines entered by hand are the given lines.
+    pub fn to_method(&self, lines: [Line; 6]) -> Result<CastingMethod, IChingError> {
+        let missing = |field: &str| IChingError::DataError(format!("method {} needs {}", self.name, field));
+        Ok(match self.name.as_str() {
+            "distribution" => CastingMethod::Distribution(LineDistribution::new(self.probabilities.ok_or_else(|| missing("probabilities"))?)?),
+            "numbers" => {
+                let [first, second] = self.numbers.as_ref().ok_or_else(|| missing("numbers"))?;
+                let parse = |number: &String| number.parse::<u64>()
+                    .map_err(|_| IChingError::DataError(format!("plum blossom number '{}' isn't a whole number", number)));
+                CastingMethod::PlumBlossom { first: parse(first)?, second: parse(second)? }
+            }
+            "time" => {
+                let time = self.time.ok_or_else(|| missing("time"))?;
+                if !(1..=12).contains(&time.month) || !(1..=30).contains(&time.day) || time.hour_branch >= 12 {
+                    return Err(IChingError::DataError(format!("{:?} isn't a time in the Chinese calendar", time)));
+                }
+                CastingMethod::PlumBlossomTime(ChineseTime {
+                    date: LunarDate { year: time.year, month: time.month, leap_month: time.leap_month, day: time.day },
+                    hour_branch: time.hour_branch as usize,
+                })
+            }
+            "lines" => CastingMethod::Manual(lines),
+            name => CastingMethod::from_arg(name)
+                .ok_or_else(|| IChingError::DataError(format!("unknown casting method '{}'", name)))?,
+        })
+    }
+}
+
+/// What kind of text a SelectedText is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextKind {
+    /// A hexagram's judgement
+    Judgement,
+    /// A hexagram's image
+    Image,
+    /// One of the six lines
+    Line,
+    /// The "use nines" or "use sixes" text
+    AllLinesChanging,
+}
+
+/// One text of the reading, and where it's from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SelectedText {
+    /// e.g. "hexagram 18, line 4"
+    pub source: String,
+    /// The King Wen number of the hexagram it's from
+    pub hexagram: u8,
+    /// Which of the hexagram's texts it is
+    pub kind: TextKind,
+    /// For lines, 1 to 6 from the bottom
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<u8>,
+    /// Whether either translation had the text. If not, `text` is empty.
+    pub available: bool,
+    /// The translation the text was found in, which is the fallback if the main one didn't have it
+    pub translation: Option<String>,
+    /// The words themselves
+    pub text: String,
+}
+
+impl SelectedText {
+    /// The text looked up in the reading's translations
+    pub fn new(text: Text, texts: &TextLookup) -> Self {
+        let (kind, position) = match text {
+            Text::Judgement(_) => (TextKind::Judgement, None),
+            Text::Image(_) => (TextKind::Image, None),
+            Text::Line(_, position) => (TextKind::Line, Some(position)),
+            Text::AllLinesChanging(_) => (TextKind::AllLinesChanging, None),
+        };
+        let found = text.text(texts);
+        SelectedText {
+            source: text.label(),
+            hexagram: text.hexagram(),
+            kind,
+            position,
+            available: found.is_some(),
+            translation: found.map(|(_, translation)| translation.to_string()),
+            text: found.map(|(text, _)| text.to_string()).unwrap_or_default(),
+        }
+    }
+}
+
+fn seed_to_string<S: Serializer>(seed: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+    seed.map(|seed| seed.to_string()).serialize(serializer)
+}
+
+fn seed_from_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|seed| seed.parse().map_err(|_| serde::de::Error::custom(format!("seed '{}' isn't a whole number", seed))))
+        .transpose()
+}
+
+impl Divination<'_> {
+    /// Every text in the reading, in the order they're shown
+    pub fn texts_shown(&self) -> Vec<Text> {
+        let number = self.present_hexagram.king_wen_number;
+        let changing = self.present_hexagram.get_changing_lines();
+        let mut shown = vec![Text::Judgement(number), Text::Image(number)];
+        shown.extend(changing.iter().map(|position| Text::Line(number, *position)));
+        if changing.len() == 6 && self.texts(self.translation_name(), self.translation()).has_all_lines_changing(number) {
+            shown.push(Text::AllLinesChanging(number));
+        }
+        if let Some(future) = &self.future_hexagram {
+            shown.extend([Text::Judgement(future.king_wen_number), Text::Image(future.king_wen_number)]);
+        }
+        shown
+    }
+
+    /// The reading with its texts, in the main translation
+    pub fn to_reading(&self) -> Reading {
+        let texts = self.texts(self.translation_name(), self.translation());
+        let primary = self.interpretation.primary_texts(&self.present_hexagram, self.future_hexagram.as_ref());
+        Reading {
+            format_version: READING_FORMAT_VERSION,
+            timestamp: self.timestamp.to_rfc3339(),
+            question: self.question.clone(),
+            method: MethodRecord::new(&self.method),
+            seed: self.seed,
+            translation: self.translation_name().to_string(),
+            interpretation: self.interpretation,
+            present: self.present_hexagram.clone(),
+            future: self.future_hexagram.clone(),
+            read_first: primary.into_iter().map(|text| SelectedText::new(text, &texts)).collect(),
+            texts: self.texts_shown().into_iter().map(|text| SelectedText::new(text, &texts)).collect(),
+        }
+    }
+}
+
+impl<'tr> Divination<'tr> {
+    /// A reading read back from JSON, to be shown in the given translation.
+    ///
+    /// Not everything comes back: the texts are looked up again in `translation`, so
+    /// `reading.translation`, `read_first` and `texts` are only there for other tools, and
+    /// the display options (nuclear hexagrams, relationships, style) aren't recorded at all.
+    /// The future hexagram always follows from the present one, so one that doesn't is an error.
+    pub fn from_reading(translation: NamedTranslation<'tr>, reading: &Reading) -> Result<Self, IChingError> {
+        if reading.format_version != READING_FORMAT_VERSION {
+            return Err(IChingError::DataError(format!(
+                "reading is format version {}, but only version {} can be read", reading.format_version, READING_FORMAT_VERSION)));
+        }
+        let timestamp = chrono::DateTime::parse_from_rfc3339(&reading.timestamp)
+            .map_err(|e| IChingError::DataError(format!("timestamp '{}': {}", reading.timestamp, e)))?;
+        let method = reading.method.to_method(reading.present.lines)?;
+
+        let mut divination = Divination::with_seed(translation, CastingMethod::Manual(reading.present.lines), 0);
+        if divination.future_hexagram != reading.future {
+            return Err(IChingError::DataError(format!("the future hexagram {} doesn't follow from the present hexagram {}",
+                reading.future.as_ref().map_or("(none)".to_string(), |future| future.king_wen_number.to_string()),
+                reading.present.king_wen_number)));
+        }
+        divination.method = method;
+        divination.seed = reading.seed;
+        divination.question = reading.question.clone();
+        divination.timestamp = timestamp;
+        divination.interpretation = reading.interpretation;
+        Ok(divination)
+    }
+}
+
+impl Serialize for Divination<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_reading().serialize(serializer)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::translation::load_hexagram_data;
+    use crate::validation::problems_against;
+
+    #[test]
+    fn test_reading_json() {
+        let translation = load_hexagram_data("data/wilhelm_baynes.json").unwrap();
+        let mut divination = Divination::with_seed(("Wilhelm/Baynes", &translation), CastingMethod::YarrowStalks, 7);
+        divination.set_question("What now?");
+        divination.interpretation = InterpretationRule::ZhuXi;
+
+        let json = serde_json::to_value(&divination).unwrap();
+        let schema: serde_json::Value = serde_json::from_str(READING_SCHEMA).unwrap();
+        assert_eq!(problems_against(&schema, &json), vec![]);
+
+        let present = &divination.present_hexagram;
+        assert_eq!(json["format_version"], 1);
+        assert_eq!(json["seed"], "7");
+        assert_eq!(json["method"]["name"], "yarrow");
+        assert_eq!(json["interpretation"], "zhu-xi");
+        assert_eq!(json["present"]["king_wen_number"], present.king_wen_number);
+        assert_eq!(json["present"]["binary"], present.binary());
+        assert_eq!(json["present"]["lines"], serde_json::json!(present.lines.map(|line| line.value())));
+        assert_eq!(json["texts"][0]["text"], translation[&present.king_wen_number].judgement);
+        assert_eq!(json["texts"][1]["kind"], "image");
+        assert_eq!(json["texts"][1]["available"], true);
+        assert_eq!(json["texts"][1]["translation"], "Wilhelm/Baynes");
+
+        // and back again
+        let reading: Reading = serde_json::from_value(json).unwrap();
+        assert_eq!(reading, divination.to_reading());
+        let replayed = Divination::from_reading(("Wilhelm/Baynes", &translation), &reading).unwrap();
+        assert_eq!(replayed.present_hexagram, divination.present_hexagram);
+        assert_eq!(replayed.future_hexagram, divination.future_hexagram);
+        assert_eq!(replayed.method, CastingMethod::YarrowStalks);
+        assert_eq!(replayed.timestamp, divination.timestamp);
+        assert_eq!(replayed.question.as_deref(), Some("What now?"));
+    }
+
+    #[test]
+    fn test_reading_round_trips() {
+        let translation = load_hexagram_data("data/wilhelm_baynes.json").unwrap();
+        let schema: serde_json::Value = serde_json::from_str(READING_SCHEMA).unwrap();
+        let time = ChineseTime {
+            date: LunarDate { year: 2023, month: 2, leap_month: true, day: 30 },
+            hour_branch: 11,
+        };
+        let lines = Hexagram::parse_lines("8 9 7 6 8 7").unwrap();
+        for method in [CastingMethod::ThreeCoins, CastingMethod::YarrowStalks, CastingMethod::YarrowStalksSimulated,
+                       CastingMethod::Distribution(LineDistribution::new([0.1, 0.2, 0.3, 0.4]).unwrap()),
+                       CastingMethod::PlumBlossom { first: u64::MAX, second: u64::MAX - 1 },
+                       CastingMethod::PlumBlossomTime(time), CastingMethod::Manual(lines)] {
+            // seeds this big come out wrong as JSON numbers
+            let divination = Divination::with_seed(("Wilhelm/Baynes", &translation), method, u64::MAX - 1);
+            let json = serde_json::to_value(&divination).unwrap();
+            assert_eq!(problems_against(&schema, &json), vec![], "{:?}", method);
+
+            let reading: Reading = serde_json::from_str(&json.to_string()).unwrap();
+            let replayed = Divination::from_reading(("Wilhelm/Baynes", &translation), &reading).unwrap();
+            assert_eq!(replayed.method, method);
+            assert_eq!(replayed.seed, divination.seed);
+            assert_eq!(replayed.present_hexagram, divination.present_hexagram);
+            assert_eq!(replayed.to_reading(), reading);
+        }
+
+        // a future hexagram that doesn't follow from the present one isn't taken on trust
+        let mut reading = Divination::with_seed(("Wilhelm/Baynes", &translation), CastingMethod::Manual(lines), 0).to_reading();
+        reading.future = None;
+        assert!(Divination::from_reading(("Wilhelm/Baynes", &translation), &reading).unwrap_err().to_string().contains("doesn't follow"));
+
+        // missing texts say so in their own field rather than in the text
+        let mut partial = translation.clone();
+        partial.get_mut(&18).unwrap().lines.clear();
+        let divination = Divination::with_seed(("Partial", &partial), CastingMethod::Manual(lines), 0);
+        let json = serde_json::to_value(&divination).unwrap();
+        assert_eq!(problems_against(&schema, &json), vec![]);
+        assert_eq!(json["texts"][2]["kind"], "line");
+        assert_eq!(json["texts"][2]["available"], false);
+        assert_eq!(json["texts"][2]["translation"], serde_json::Value::Null);
+        assert_eq!(json["texts"][2]["text"], "");
+    }
+
+    #[test]
+    fn test_every_hexagram_round_trips() {
+        for binary in 0..64 {
+            let lines = std::array::from_fn(|i| if binary & (1 << i) != 0 { crate::line::Line::ChangingYang } else { crate::line::Line::StaticYin });
+            let hexagram = Hexagram::from_lines(lines);
+            let json = serde_json::to_string(&hexagram).unwrap();
+            assert_eq!(serde_json::from_str::<Hexagram>(&json).unwrap(), hexagram);
+        }
+
+        // everything has to agree with the lines
+        let mut json = serde_json::to_value(Hexagram::from_lines(Hexagram::parse_lines("8 9 7 6 8 7").unwrap())).unwrap();
+        assert_eq!(json["king_wen_number"], 18);
+        assert_eq!(json["changing_lines"], serde_json::json!([2, 4]));
+        json["king_wen_number"] = 17.into();
+        assert!(serde_json::from_value::<Hexagram>(json.clone()).unwrap_err().to_string().contains("hexagram 17 doesn't agree"));
+        json["king_wen_number"] = 18.into();
+        json["lines"][0] = 5.into();
+        assert!(serde_json::from_value::<Hexagram>(json).unwrap_err().to_string().contains("line value 5 is not 6, 7, 8 or 9"));
+    }
+}
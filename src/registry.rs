@@ -0,0 +1,273 @@
+//! Every translation we can find, by name. Translations are discovered in the user's own
+//! directory, `$XDG_DATA_HOME/rs-iching` (usually `~/.local/share/rs-iching`), and any others asked for,
+//! and go by their `metadata.translation_source`. Partial translations that name another
+//! in `metadata.overlays` are laid over it once everything else has been found.
+//! The default translation is compiled in, and a file with the same name replaces it.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::error::IChingError;
+use crate::overlay::{LayeredTranslation, Overlay, Provenance};
+use crate::translation::{embedded_iching_data, iching_data_from_json, IChingTranslation, TranslationSet};
+
+/// The translation readings are shown in unless another is picked
+pub const DEFAULT_TRANSLATION: &str = "Wilhelm/Baynes";
+
+/// Translations keyed by name
+#[derive(Debug, Default)]
+pub struct TranslationRegistry {
+    translations: BTreeMap<String, IChingTranslation>,
+    // for the translations that were built up from overlays
+    provenance: BTreeMap<String, Provenance>,
+}
+
+impl TranslationRegistry {
+    /// No translations at all, not even the compiled-in one
+    pub fn new() -> Self {
+        TranslationRegistry::default()
+    }
+
+    /// Where to look for translations unless told otherwise: just the user's directory.
+    /// The current directory isn't searched, so what's found doesn't depend on where it's run from;
+    /// pass any other directories to discover after these.
+    pub fn default_dirs() -> Vec<PathBuf> {
+        let user_data = std::env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()).map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")));
+        user_data.map(|dir| dir.join("rs-iching")).into_iter().collect()
+    }
+
+    /// Just the compiled-in default translation
+    pub fn embedded() -> Result<Self, IChingError> {
+        let mut registry = TranslationRegistry::new();
+        let data = embedded_iching_data()?;
+        let name = data.source().unwrap_or(DEFAULT_TRANSLATION).to_string();
+        registry.insert(&name, data.into_translation());
+        Ok(registry)
+    }
+
+    /// The compiled-in translation plus every translation in the given directories.
+    /// Directories that don't exist are skipped, and files that can't be loaded, the compiled-in
+    /// one included, are returned alongside the registry rather than stopping the rest.
+    pub fn discover(dirs: &[PathBuf]) -> (Self, Vec<(PathBuf, IChingError)>) {
+        let mut failures = Vec::new();
+        let mut registry = TranslationRegistry::embedded().unwrap_or_else(|e| {
+            failures.push((PathBuf::from("(compiled-in translation)"), e));
+            TranslationRegistry::new()
+        });
+        // overlays wait until the translations they go over have been loaded
+        let mut overlays = Vec::new();
+
+        for dir in dirs {
+            let Ok(entries) = fs::read_dir(dir) else { continue };
+            let mut files: Vec<PathBuf> = entries.filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                // the schema lives alongside the translations but isn't one
+                .filter(|path| !path.to_string_lossy().ends_with("_schema.json"))
+                .collect();
+            files.sort();
+
+            for file in files {
+                let result = read_json(&file).and_then(|json| {
+                    if Overlay::is_overlay(&json) {
+                        overlays.push((file.clone(), Overlay::from_json(json, &file_name(&file))?));
+                        Ok(())
+                    } else {
+                        registry.insert_json(json, &file).map(|_| ())
+                    }
+                });
+                if let Err(e) = result {
+                    failures.push((file, e));
+                }
+            }
+        }
+
+        for (file, overlay) in overlays {
+            if let Err(e) = registry.apply_overlay(overlay, None) {
+                failures.push((file, e));
+            }
+        }
+
+        (registry, failures)
+    }
+
+    /// Load one translation or overlay file, returning the name it's registered under:
+    /// its translation_source, or the file name if it doesn't have one
+    pub fn load_file(&mut self, path: &Path) -> Result<String, IChingError> {
+        let json = read_json(path)?;
+        if Overlay::is_overlay(&json) {
+            self.apply_overlay(Overlay::from_json(json, &file_name(path))?, None)
+        } else {
+            self.insert_json(json, path)
+        }
+    }
+
+    fn insert_json(&mut self, json: Value, path: &Path) -> Result<String, IChingError> {
+        let data = iching_data_from_json(json)?;
+        let name = data.source().map(str::to_string).unwrap_or_else(|| file_name(path));
+        self.insert(&name, data.into_translation());
+        Ok(name)
+    }
+
+    /// Lay an overlay over the translation it names, or over the given one if it doesn't say,
+    /// or the default. The result goes by the overlay's name.
+    pub fn apply_overlay(&mut self, overlay: Overlay, base: Option<&str>) -> Result<String, IChingError> {
+        let base_name = overlay.base.as_deref().or(base).unwrap_or(DEFAULT_TRANSLATION);
+        let (base_name, base) = self.select(&[base_name])?.remove(0);
+        let layered = LayeredTranslation::new(base_name, base, std::slice::from_ref(&overlay))?;
+
+        self.insert(&overlay.name, layered.translation);
+        self.provenance.insert(overlay.name.clone(), layered.provenance);
+        Ok(overlay.name)
+    }
+
+    /// Which fields came from where, for a translation built from an overlay
+    pub fn provenance(&self, name: &str) -> Option<&Provenance> {
+        self.provenance.get(name)
+    }
+
+    /// Add a translation, replacing any of the same name
+    pub fn insert(&mut self, name: &str, translation: IChingTranslation) {
+        self.translations.insert(name.to_string(), translation);
+        self.provenance.remove(name);
+    }
+
+    /// The translation with exactly this name
+    pub fn get(&self, name: &str) -> Option<&IChingTranslation> {
+        self.translations.get(name)
+    }
+
+    /// The names of all the translations, in alphabetical order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.translations.keys().map(String::as_str)
+    }
+
+    /// Whether there are no translations at all
+    pub fn is_empty(&self) -> bool {
+        self.translations.is_empty()
+    }
+
+    /// The named translations, in the order given. Names are matched exactly first,
+    /// then ignoring case; an unknown name is an error.
+    pub fn select(&self, names: &[&str]) -> Result<TranslationSet<'_>, IChingError> {
+        names.iter().map(|name| {
+            self.translations.get_key_value(*name)
+                .or_else(|| self.translations.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)))
+                .map(|(key, translation)| (key.as_str(), translation))
+                .ok_or_else(|| IChingError::InputError(format!(
+                    "no translation called '{}', expected one of: {}", name, self.names().collect::<Vec<_>>().join(", "))))
+        }).collect()
+    }
+
+    /// The default translation, or failing that the first one there is
+    pub fn default_set(&self) -> TranslationSet<'_> {
+        self.select(&[DEFAULT_TRANSLATION]).ok()
+            .or_else(|| self.translations.iter().next().map(|(name, translation)| vec![(name.as_str(), translation)]))
+            .unwrap_or_default()
+    }
+}
+
+
+fn read_json(path: &Path) -> Result<Value, IChingError> {
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_stem().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty directory of the test's own, so tests and test runs don't trip over each other
+    fn empty_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rs-iching-{}-{}", std::process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A scratch directory holding a copy of the bundled translation under another name
+    fn scratch_dir(test: &str) -> PathBuf {
+        let dir = empty_dir(test);
+
+        let mut json: serde_json::Value = serde_json::from_str(&fs::read_to_string("data/wilhelm_baynes.json").unwrap()).unwrap();
+        json["metadata"]["translation_source"] = "Legge".into();
+        json["hexagrams"][0]["judgement"] = "The Creative. Sublime success.".into();
+        fs::write(dir.join("legge.json"), json.to_string()).unwrap();
+        fs::write(dir.join("broken.json"), "{").unwrap();
+        fs::write(dir.join("notes.txt"), "not a translation").unwrap();
+        // sorts before legge.json, but still has to wait for it
+        fs::write(dir.join("a_legge_lines.json"), r#"{
+            "metadata": {"translation_source": "Legge with lines", "overlays": "Legge"},
+            "hexagrams": [{"king_wen_number": 1, "lines": [{"position": 1, "text": "The dragon lies hid in the deep."}]}]
+        }"#).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_discover() {
+        let dir = scratch_dir("test-discover");
+        let (registry, failures) = TranslationRegistry::discover(&[PathBuf::from("data"), dir.clone(), dir.join("missing")]);
+
+        assert_eq!(registry.names().collect::<Vec<_>>(), vec!["Legge", "Legge with lines", "Wilhelm/Baynes"]);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, dir.join("broken.json"));
+        assert_eq!(registry.get("Legge").unwrap()[&1].judgement, "The Creative. Sublime success.");
+
+        let layered = registry.get("Legge with lines").unwrap();
+        assert_eq!(layered[&1].judgement, "The Creative. Sublime success.");
+        assert_eq!(layered[&1].lines[0].text, "The dragon lies hid in the deep.");
+        let provenance = registry.provenance("Legge with lines").unwrap();
+        assert_eq!(provenance.source_of("1/lines/1/text"), "Legge with lines");
+        assert_eq!(provenance.source_of("1/judgement"), "Legge");
+        assert!(registry.provenance("Legge").is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_default_dirs() {
+        // only the user's own directory, wherever it's run from
+        let dirs = TranslationRegistry::default_dirs();
+        assert!(dirs.len() <= 1);
+        assert!(dirs.iter().all(|dir| dir.ends_with("rs-iching")));
+    }
+
+    #[test]
+    fn test_embedded_default() {
+        // nothing on disk, but the default is always there
+        let (registry, failures) = TranslationRegistry::discover(&[]);
+        assert!(failures.is_empty());
+        assert_eq!(registry.names().collect::<Vec<_>>(), vec![DEFAULT_TRANSLATION]);
+        assert_eq!(registry.default_set()[0].1.len(), 64);
+
+        // a file of the same name replaces it
+        let dir = empty_dir("test-embedded-default");
+        let mut json: serde_json::Value = serde_json::from_str(crate::translation::EMBEDDED_TRANSLATION).unwrap();
+        json["hexagrams"][0]["image"] = "Replaced.".into();
+        fs::write(dir.join("mine.json"), json.to_string()).unwrap();
+
+        let (registry, _) = TranslationRegistry::discover(std::slice::from_ref(&dir));
+        assert_eq!(registry.names().collect::<Vec<_>>(), vec![DEFAULT_TRANSLATION]);
+        assert_eq!(registry.get(DEFAULT_TRANSLATION).unwrap()[&1].image, "Replaced.");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_select() {
+        let dir = scratch_dir("test-select");
+        let (registry, _) = TranslationRegistry::discover(&[PathBuf::from("data"), dir.clone()]);
+
+        let set = registry.select(&["legge", "Wilhelm/Baynes"]).unwrap();
+        assert_eq!(set.iter().map(|(name, _)| *name).collect::<Vec<_>>(), vec!["Legge", "Wilhelm/Baynes"]);
+        assert_eq!(registry.default_set()[0].0, DEFAULT_TRANSLATION);
+
+        let error = registry.select(&["Blofeld"]).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid input: no translation called 'Blofeld', expected one of: Legge, Legge with lines, Wilhelm/Baynes");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,531 @@
+//! Showing a reading in different formats: plain text for the terminal, and Markdown or
+//! a self-contained HTML page for pasting into notes and wikis
+
+use std::fmt;
+
+use crate::divination::{Divination, SIDE_BY_SIDE_WIDTH};
+use crate::hexagram::{Hexagram, RenderStyle};
+use crate::line::Line;
+use crate::translation::TextLookup;
+
+/// A text as it's shown: as it is from the main translation, marked with where it's from
+/// if the fallback had it, or a note that neither did
+pub(crate) fn shown_text(texts: &TextLookup, found: Option<(&str, &str)>) -> String {
+    match found {
+        Some((text, source)) if source == texts.name => text.to_string(),
+        Some((text, source)) => format!("{} (from {})", text, source),
+        None => format!("text unavailable in {}", texts.name),
+    }
+}
+
+/// Turns a reading into a document
+pub trait Renderer {
+    /// The whole reading as a document
+    fn render(&self, divination: &Divination) -> String;
+}
+
+/// The formats readings can be rendered in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Plain text for the terminal, the same as printing the divination
+    #[default]
+    Text,
+    /// Markdown, see MarkdownRenderer
+    Markdown,
+    /// A whole HTML page, see HtmlRenderer
+    Html,
+}
+
+impl OutputFormat {
+    /// The format from its command line name: text, markdown (or md) or html
+    pub fn from_arg(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(OutputFormat::Text),
+            "markdown" | "md" => Some(OutputFormat::Markdown),
+            "html" => Some(OutputFormat::Html),
+            _ => None,
+        }
+    }
+
+    /// The renderer for the format
+    pub fn renderer(&self) -> Box<dyn Renderer> {
+        match self {
+            OutputFormat::Text => Box::new(TextRenderer),
+            OutputFormat::Markdown => Box::new(MarkdownRenderer),
+            OutputFormat::Html => Box::new(HtmlRenderer),
+        }
+    }
+}
+
+/// Plain text, as printed to the terminal. Several translations go side by side.
+pub struct TextRenderer;
+
+impl Renderer for TextRenderer {
+    fn render(&self, divination: &Divination) -> String {
+        let mut out = String::new();
+        self.write(&mut out, divination).expect("writing to a String can't fail");
+        out
+    }
+}
+
+impl TextRenderer {
+    /// The question, how it was cast, and the body in each translation
+    fn write(&self, f: &mut impl fmt::Write, divination: &Divination) -> fmt::Result {
+        if let Some(question) = &divination.question {
+            write!(f, "QUESTION: {}\n\n", question)?;
+        }
+        match divination.seed {
+            Some(seed) => write!(f, "Cast with {} (seed {})\n\n", divination.method.name(), seed)?,
+            None => write!(f, "Cast with {}\n\n", divination.method.name())?,
+        }
+
+        if divination.translations().len() < 2 {
+            return self.write_body(f, divination, &divination.texts(divination.translation_name(), divination.translation()));
+        }
+        let columns = divination.translations().iter().map(|(name, translation)| {
+            let mut column = format!("{}\n{}\n\n", name, "=".repeat(name.chars().count()));
+            self.write_body(&mut column, divination, &divination.texts(name, translation)).map(|_| column)
+        }).collect::<Result<Vec<_>, _>>()?;
+        write!(f, "{}", side_by_side(&columns, SIDE_BY_SIDE_WIDTH))
+    }
+
+    /// Everything after the question and the casting method, in the given translation
+    fn write_body(&self, f: &mut impl fmt::Write, divination: &Divination, texts: &TextLookup) -> fmt::Result {
+        let primary = divination.interpretation.primary_texts(&divination.present_hexagram, divination.future_hexagram.as_ref());
+        if !primary.is_empty() {
+            writeln!(f, "READ FIRST: {}", divination.interpretation.explain(&divination.present_hexagram))?;
+            for text in &primary {
+                writeln!(f, ">> {}: {}", text.label(), shown_text(texts, text.text(texts)))?;
+            }
+            writeln!(f)?;
+        }
+        // pass the translation object to the describe function
+        writeln!(f, "{}", divination.present_hexagram.describe(texts, divination.render_style))?;
+        self.write_related(f, divination, &divination.present_hexagram, texts)?;
+
+        // print changing lines
+        let number = divination.present_hexagram.king_wen_number;
+        for index in &divination.present_hexagram.get_changing_lines() {
+            write!(f, "line {} - {}\n\n", index, shown_text(texts, texts.line(number, *index)))?;
+        }
+
+        // the Creative and the Receptive have a seventh text for when every line moves (1 -> 2 and 2 -> 1)
+        if divination.present_hexagram.get_changing_lines().len() == 6 && texts.has_all_lines_changing(number) {
+            write!(f, "All lines changing: {}\n\n", shown_text(texts, texts.all_lines_changing(number)))?;
+        }
+
+        if let Some(future) = &divination.future_hexagram {
+            write!(f, "-- changing to -- \n{}", future.describe(texts, divination.render_style))?;
+            self.write_related(f, divination, future, texts)
+        } else {
+            writeln!(f, "-- unchanging --")
+        }
+    }
+
+    /// The nuclear hexagram and the relationships of a hexagram, if the reading asks for them
+    fn write_related(&self, f: &mut impl fmt::Write, divination: &Divination, hexagram: &Hexagram, texts: &TextLookup) -> fmt::Result {
+        let related = Related::new(hexagram, texts);
+        if divination.show_nuclear {
+            write!(f, "NUCLEAR HEXAGRAM {}\n{}\n\n", related.nuclear_title, related.nuclear_judgement)?;
+        }
+        if divination.show_relationships {
+            writeln!(f, "RELATIONSHIPS OF HEXAGRAM {}", hexagram.king_wen_number)?;
+            for relationship in &related.relationships {
+                writeln!(f, "{}", relationship)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Word wrap one line of text. Words too long for a line of their own are broken up.
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    // a word can always have at least one character per line
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in line.split(' ') {
+        let mut word = word.to_string();
+        while word.chars().count() > width {
+            if !current.is_empty() {
+                lines.push(std::mem::take(&mut current));
+            }
+            let rest = word.split_off(word.char_indices().nth(width).unwrap().0);
+            lines.push(word);
+            word = rest;
+        }
+        if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(&word);
+    }
+    lines.push(current);
+    lines
+}
+
+// narrower than this and side by side columns are mostly wrapping
+const MIN_COLUMN_WIDTH: usize = 20;
+
+/// Texts in columns, each wrapped to fit, separated by " | ". If there isn't room for
+/// MIN_COLUMN_WIDTH each, the columns go one after another instead.
+fn side_by_side(columns: &[String], width: usize) -> String {
+    if columns.is_empty() {
+        return String::new();
+    }
+    let column_width = width.saturating_sub(3 * (columns.len() - 1)) / columns.len();
+    if column_width < MIN_COLUMN_WIDTH {
+        let width = width.max(MIN_COLUMN_WIDTH);
+        return columns.iter()
+            .map(|column| column.lines().flat_map(|line| wrap(line, width)).map(|line| line + "\n").collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+    let wrapped: Vec<Vec<String>> = columns.iter()
+        .map(|column| column.lines().flat_map(|line| wrap(line, column_width)).collect())
+        .collect();
+    let rows = wrapped.iter().map(Vec::len).max().unwrap_or(0);
+
+    let mut result = String::new();
+    for row in 0..rows {
+        let cells: Vec<String> = wrapped.iter().map(|lines| {
+            let cell = lines.get(row).map(String::as_str).unwrap_or("");
+            format!("{}{}", cell, " ".repeat(column_width - cell.chars().count()))
+        }).collect();
+        result.push_str(cells.join(" | ").trim_end());
+        result.push('\n');
+    }
+    result
+}
+
+/// The hexagrams related to one in the reading, worded the same way in every format
+struct Related {
+    nuclear_title: String,
+    nuclear_judgement: String,
+    /// The inverse (zong gua) and the opposite (cuo gua), as "label: title"
+    relationships: [String; 2],
+}
+
+impl Related {
+    fn new(hexagram: &Hexagram, texts: &TextLookup) -> Self {
+        let nuclear = hexagram.nuclear().king_wen_number;
+        let inverse = hexagram.inverse().king_wen_number;
+        Related {
+            nuclear_title: texts.title(nuclear),
+            nuclear_judgement: shown_text(texts, texts.judgement(nuclear)),
+            relationships: [
+                if inverse == hexagram.king_wen_number {
+                    "Inverse (zong gua): the same, it reads alike upside down".to_string()
+                } else {
+                    format!("Inverse (zong gua): {}", texts.title(inverse))
+                },
+                format!("Opposite (cuo gua): {}", texts.title(hexagram.opposite().king_wen_number)),
+            ],
+        }
+    }
+}
+
+/// The pieces a structured document is built from. Markdown and HTML lay out a reading
+/// the same way and only differ in how the pieces are written.
+trait Markup {
+    fn heading(&self, out: &mut String, level: usize, text: &str);
+    fn paragraph(&self, out: &mut String, text: &str);
+    fn list(&self, out: &mut String, items: &[String]);
+    /// The lines of the hexagram, with the moving ones marked
+    fn diagram(&self, out: &mut String, hexagram: &Hexagram, style: RenderStyle);
+    /// The finished body as a whole document
+    fn document(&self, title: &str, body: String) -> String;
+}
+
+/// Lay out a reading with headings for each hexagram and its texts. Several translations
+/// follow one another, each under its own heading.
+fn render_markup(markup: &impl Markup, divination: &Divination) -> String {
+    let mut out = String::new();
+    let title = match &divination.question {
+        Some(question) => question.clone(),
+        None => "I Ching reading".to_string(),
+    };
+    markup.heading(&mut out, 1, &title);
+    markup.paragraph(&mut out, &match divination.seed {
+        Some(seed) => format!("Cast with {} (seed {})", divination.method.name(), seed),
+        None => format!("Cast with {}", divination.method.name()),
+    });
+
+    let translations = divination.translations();
+    let level = if translations.len() > 1 { 3 } else { 2 };
+    for (name, translation) in translations {
+        if translations.len() > 1 {
+            markup.heading(&mut out, 2, name);
+        }
+        let texts = divination.texts(name, translation);
+
+        let primary = divination.interpretation.primary_texts(&divination.present_hexagram, divination.future_hexagram.as_ref());
+        if !primary.is_empty() {
+            markup.heading(&mut out, level, "Read first");
+            markup.paragraph(&mut out, divination.interpretation.explain(&divination.present_hexagram));
+            let items: Vec<String> = primary.iter().map(|text| format!("{}: {}", text.label(), shown_text(&texts, text.text(&texts)))).collect();
+            markup.list(&mut out, &items);
+        }
+
+        markup_hexagram(markup, &mut out, divination, &divination.present_hexagram, &texts, level, "Hexagram");
+        match &divination.future_hexagram {
+            Some(future) => markup_hexagram(markup, &mut out, divination, future, &texts, level, "Changing to hexagram"),
+            None => markup.paragraph(&mut out, "No lines are changing."),
+        }
+    }
+    markup.document(&title, out)
+}
+
+/// One hexagram: its names, diagram and texts, and the changing lines if it has any
+fn markup_hexagram(markup: &impl Markup, out: &mut String, divination: &Divination, hexagram: &Hexagram,
+                   texts: &TextLookup, level: usize, heading: &str) {
+    let number = hexagram.king_wen_number;
+    markup.heading(out, level, &format!("{} {}", heading, texts.title(number)));
+
+    let mut names: Vec<&str> = Vec::new();
+    if let Some(name) = texts.hexagram_name(number) {
+        names.extend(name.chinese.as_deref());
+        names.extend(name.pinyin.as_deref());
+    }
+    let trigrams = format!("{} over {}", Hexagram::TRIGRAMS[Hexagram::calculate_trigram(&hexagram.lines[3..6])],
+        Hexagram::TRIGRAMS[Hexagram::calculate_trigram(&hexagram.lines[0..3])]);
+    names.push(&trigrams);
+    markup.paragraph(out, &names.join(" · "));
+    markup.diagram(out, hexagram, divination.render_style);
+
+    markup.heading(out, level + 1, "The Judgement");
+    markup.paragraph(out, &shown_text(texts, texts.judgement(number)));
+    markup.heading(out, level + 1, "The Image");
+    markup.paragraph(out, &shown_text(texts, texts.image(number)));
+
+    let changing = hexagram.get_changing_lines();
+    if !changing.is_empty() {
+        markup.heading(out, level + 1, "Changing lines");
+        let mut items: Vec<String> = changing.iter()
+            .map(|position| format!("Line {}: {}", position, shown_text(texts, texts.line(number, *position))))
+            .collect();
+        if changing.len() == 6 && texts.has_all_lines_changing(number) {
+            items.push(format!("All lines changing: {}", shown_text(texts, texts.all_lines_changing(number))));
+        }
+        markup.list(out, &items);
+    }
+
+    let related = Related::new(hexagram, texts);
+    if divination.show_nuclear {
+        markup.heading(out, level + 1, "Nuclear hexagram");
+        markup.paragraph(out, &format!("{}. {}", related.nuclear_title, related.nuclear_judgement));
+    }
+    if divination.show_relationships {
+        markup.heading(out, level + 1, "Relationships");
+        markup.list(out, &related.relationships);
+    }
+}
+
+/// Markdown, with the hexagram drawn in a code block in the reading's render style
+pub struct MarkdownRenderer;
+
+impl Renderer for MarkdownRenderer {
+    fn render(&self, divination: &Divination) -> String {
+        render_markup(self, divination)
+    }
+}
+
+impl Markup for MarkdownRenderer {
+    // headings hold questions and translation names, which can be anything
+    fn heading(&self, out: &mut String, level: usize, text: &str) {
+        out.push_str(&format!("{} {}\n\n", "#".repeat(level), escape_markdown(text)));
+    }
+
+    fn paragraph(&self, out: &mut String, text: &str) {
+        out.push_str(&format!("{}\n\n", text));
+    }
+
+    fn list(&self, out: &mut String, items: &[String]) {
+        for item in items {
+            out.push_str(&format!("- {}\n", item));
+        }
+        out.push('\n');
+    }
+
+    fn diagram(&self, out: &mut String, hexagram: &Hexagram, style: RenderStyle) {
+        out.push_str(&format!("```\n{}```\n\n", hexagram.diagram_in(style)));
+    }
+
+    fn document(&self, _title: &str, body: String) -> String {
+        body
+    }
+}
+
+/// A whole HTML page with its own stylesheet, so it can be saved or pasted as it is
+pub struct HtmlRenderer;
+
+impl Renderer for HtmlRenderer {
+    fn render(&self, divination: &Divination) -> String {
+        render_markup(self, divination)
+    }
+}
+
+const HTML_STYLE: &str = "\
+body { font-family: Georgia, serif; max-width: 40em; margin: 2em auto; line-height: 1.5; }
+.hexagram { margin: 1em 0; }
+.line { position: relative; display: flex; justify-content: space-between; width: 6em; height: 0.6em; margin: 0.4em 0; }
+.line.yang { background: #222; }
+.line.yin span { width: 2.6em; background: #222; }
+.line.moving::after { position: absolute; left: 6.5em; top: -0.5em; color: #b22; }
+.line.yang.moving::after { content: \"○\"; }
+.line.yin.moving::after { content: \"×\"; }
+";
+
+impl Markup for HtmlRenderer {
+    fn heading(&self, out: &mut String, level: usize, text: &str) {
+        out.push_str(&format!("<h{level}>{}</h{level}>\n", escape_html(text)));
+    }
+
+    fn paragraph(&self, out: &mut String, text: &str) {
+        out.push_str(&format!("<p>{}</p>\n", escape_html(text)));
+    }
+
+    fn list(&self, out: &mut String, items: &[String]) {
+        out.push_str("<ul>\n");
+        for item in items {
+            out.push_str(&format!("<li>{}</li>\n", escape_html(item)));
+        }
+        out.push_str("</ul>\n");
+    }
+
+    // always drawn with CSS, whatever the render style
+    fn diagram(&self, out: &mut String, hexagram: &Hexagram, _style: RenderStyle) {
+        out.push_str(&format!("<div class=\"hexagram\" title=\"hexagram {}\">\n", hexagram.king_wen_number));
+        for (i, line) in hexagram.lines.iter().enumerate().rev() {
+            let class = match line {
+                Line::StaticYang => "yang",
+                Line::StaticYin => "yin",
+                Line::ChangingYang => "yang moving",
+                Line::ChangingYin => "yin moving",
+            };
+            let inside = if matches!(line, Line::StaticYin | Line::ChangingYin) { "<span></span><span></span>" } else { "" };
+            out.push_str(&format!("<div class=\"line {}\" title=\"line {}: {}\">{}</div>\n", class, i + 1, line.describe(), inside));
+        }
+        out.push_str("</div>\n");
+    }
+
+    fn document(&self, title: &str, body: String) -> String {
+        format!("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+            escape_html(title), HTML_STYLE, body)
+    }
+}
+
+/// Backslashes before anything Markdown would take as emphasis, code, a heading, a link or inline HTML
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '#' | '*' | '_' | '`' | '<' | '>' | '[' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::casting::CastingMethod;
+    use crate::translation::load_hexagram_data;
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("the quick brown fox", 10), vec!["the quick", "brown fox"]);
+        assert_eq!(wrap("", 10), vec![""]);
+        assert_eq!(wrap("abcdefghijkl xy", 5), vec!["abcde", "fghij", "kl xy"]);
+        assert_eq!(wrap("ab c", 0), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_side_by_side_narrow() {
+        let columns = ["one\ntwo".to_string(), "three".to_string()];
+        assert_eq!(side_by_side(&columns, 50), "one                     | three\ntwo                     |\n");
+        // too narrow for two columns, so one after the other
+        for width in [0, 1, 40] {
+            assert_eq!(side_by_side(&columns, width), "one\ntwo\n\nthree\n");
+        }
+        assert_eq!(side_by_side(&[], 80), "");
+    }
+
+    #[test]
+    fn test_shown_text() {
+        let wilhelm = load_hexagram_data("data/wilhelm_baynes.json").unwrap();
+        let empty = crate::translation::IChingTranslation::new();
+        let texts = TextLookup { name: "Empty", translation: &empty, fallback: Some(("Wilhelm/Baynes", &wilhelm)) };
+        assert_eq!(shown_text(&texts, Some(("Sublime success.", "Empty"))), "Sublime success.");
+        assert_eq!(shown_text(&texts, texts.image(1)), format!("{} (from Wilhelm/Baynes)", wilhelm[&1].image));
+        assert_eq!(shown_text(&TextLookup::new("Empty", &empty), None), "text unavailable in Empty");
+    }
+
+
+    fn reading(translation: &crate::translation::IChingTranslation) -> Divination<'_> {
+        // 18 with lines 2 and 4 moving, changing to 56
+        let lines = Hexagram::parse_lines("8 9 7 6 8 7").unwrap();
+        let mut divination = Divination::with_seed(("Wilhelm/Baynes", translation), CastingMethod::Manual(lines), 0);
+        divination.set_question("Should I <fix> it?");
+        divination
+    }
+
+    #[test]
+    fn test_text_renderer() {
+        let translation = load_hexagram_data("data/wilhelm_baynes.json").unwrap();
+        let divination = reading(&translation);
+        assert_eq!(TextRenderer.render(&divination), divination.to_string());
+        assert!(OutputFormat::Text.renderer().render(&divination).starts_with("QUESTION: Should I <fix> it?\n\nCast with"));
+    }
+
+    #[test]
+    fn test_markdown_renderer() {
+        let translation = load_hexagram_data("data/wilhelm_baynes.json").unwrap();
+        let mut divination = reading(&translation);
+        divination.show_relationships = true;
+        let markdown = MarkdownRenderer.render(&divination);
+
+        assert!(markdown.starts_with("# Should I \\<fix\\> it?\n\nCast with lines entered by hand\n\n## Hexagram 18: Work on What Has Been Spoiled\n\n"));
+        assert!(markdown.contains("\n\n蠱 · Gù · Mountain over Wind\n\n```\n6 ---------\n"));
+        assert!(markdown.contains(&format!("### The Judgement\n\n{}\n\n### The Image\n\n", translation[&18].judgement)));
+        assert!(markdown.contains(&format!("### Changing lines\n\n- Line 2: {}\n- Line 4: {}\n\n", translation[&18].lines[1].text, translation[&18].lines[3].text)));
+        assert!(markdown.contains("## Changing to hexagram 56: The Wanderer\n\n"));
+        assert!(markdown.contains("- Opposite (cuo gua): 17: Following\n"));
+
+        // questions are the user's own words, and mustn't turn into markup
+        divination.set_question("# *Why* not `now`_? [see](x)");
+        assert!(MarkdownRenderer.render(&divination).starts_with("# \\# \\*Why\\* not \\`now\\`\\_? \\[see\\](x)\n\n"));
+    }
+
+    #[test]
+    fn test_html_renderer() {
+        let translation = load_hexagram_data("data/wilhelm_baynes.json").unwrap();
+        let html = HtmlRenderer.render(&reading(&translation));
+
+        assert!(html.starts_with("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>Should I &lt;fix&gt; it?</title>"));
+        assert!(html.ends_with("</body>\n</html>\n"));
+        assert!(html.contains("<h2>Hexagram 18: Work on What Has Been Spoiled</h2>\n<p>蠱 · Gù · Mountain over Wind</p>\n<div class=\"hexagram\" title=\"hexagram 18\">\n<div class=\"line yang\" title=\"line 6: young yang\"></div>\n"));
+        assert!(html.contains("<div class=\"line yin moving\" title=\"line 4: old yin\"><span></span><span></span></div>"));
+        assert!(html.contains("<div class=\"line yang moving\" title=\"line 2: old yang\"></div>"));
+        assert!(html.contains("<h3>The Image</h3>"));
+        // six lines to each of the two hexagrams
+        assert_eq!(html.matches("<div class=\"line ").count(), 12);
+        assert_eq!(html.matches("<h3>").count(), html.matches("</h3>").count());
+    }
+
+    #[test]
+    fn test_output_format_from_arg() {
+        assert_eq!(OutputFormat::from_arg("md"), Some(OutputFormat::Markdown));
+        assert_eq!(OutputFormat::from_arg("html"), Some(OutputFormat::Html));
+        assert_eq!(OutputFormat::from_arg("pdf"), None);
+    }
+}
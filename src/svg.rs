@@ -0,0 +1,174 @@
+//! SVG pictures of hexagrams, and of a whole reading with the present hexagram beside the future one.
+//! Everything is laid out on a fixed grid, so the same hexagram always gives the same file.
+
+use std::fmt::Write;
+
+use crate::hexagram::Hexagram;
+use crate::line::Line;
+
+const BAR_WIDTH: usize = 120;
+const BAR_HEIGHT: usize = 14;
+// from the top of one line to the top of the next
+const LINE_PITCH: usize = 24;
+const BROKEN_GAP: usize = 20;
+// room to the right of the lines for the circles and crosses
+const MARKER_SPACE: usize = 36;
+const LABEL_WIDTH: usize = 110;
+const CAPTION_HEIGHT: usize = 28;
+const MARGIN: usize = 20;
+// between the present and future hexagrams, for the arrow
+const ARROW_SPACE: usize = 60;
+
+const LINES_HEIGHT: usize = 5 * LINE_PITCH + BAR_HEIGHT;
+const HEXAGRAM_HEIGHT: usize = LINES_HEIGHT + CAPTION_HEIGHT;
+
+impl Hexagram {
+    /// The hexagram as an SVG image: solid and broken bars, a circle on moving yang lines
+    /// and a cross on moving yin lines, and the trigrams beside them if asked for
+    pub fn to_svg(&self, trigram_labels: bool) -> String {
+        let width = 2 * MARGIN + svg_width(trigram_labels);
+        let mut svg = svg_start(width, 2 * MARGIN + HEXAGRAM_HEIGHT);
+        svg.push_str(&self.svg_group(MARGIN, MARGIN, trigram_labels));
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// A whole reading as an SVG image: this hexagram, an arrow and the future hexagram,
+    /// or just this one if no lines are moving
+    pub fn reading_svg(&self, future: Option<&Hexagram>, trigram_labels: bool) -> String {
+        let Some(future) = future else {
+            return self.to_svg(trigram_labels);
+        };
+        let hexagram_width = svg_width(trigram_labels);
+        let width = 2 * MARGIN + 2 * hexagram_width + ARROW_SPACE;
+        let mut svg = svg_start(width, 2 * MARGIN + HEXAGRAM_HEIGHT);
+        svg.push_str(&self.svg_group(MARGIN, MARGIN, trigram_labels));
+
+        // the arrow, level with the middle of the lines
+        let (start, end, y) = (MARGIN + hexagram_width + 10, MARGIN + hexagram_width + ARROW_SPACE - 10, MARGIN + LINES_HEIGHT / 2);
+        writeln!(svg, r##"<path d="M{start} {y} H{end} M{} {} L{end} {y} L{} {}" fill="none" stroke="#222" stroke-width="2"/>"##,
+            end - 8, y - 6, end - 8, y + 6).unwrap();
+
+        svg.push_str(&future.svg_group(MARGIN + hexagram_width + ARROW_SPACE, MARGIN, trigram_labels));
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// The lines, markers, labels and caption, with the top left corner at x, y
+    fn svg_group(&self, x: usize, y: usize, trigram_labels: bool) -> String {
+        let mut group = String::new();
+        writeln!(group, r#"<g transform="translate({x} {y})">"#).unwrap();
+
+        group.push_str("<g fill=\"#222\">\n");
+        for (i, line) in self.lines.iter().rev().enumerate() {
+            let top = i * LINE_PITCH;
+            match line {
+                Line::StaticYang | Line::ChangingYang =>
+                    writeln!(group, r#"<rect x="0" y="{top}" width="{BAR_WIDTH}" height="{BAR_HEIGHT}"/>"#).unwrap(),
+                Line::StaticYin | Line::ChangingYin => {
+                    let half = (BAR_WIDTH - BROKEN_GAP) / 2;
+                    writeln!(group, r#"<rect x="0" y="{top}" width="{half}" height="{BAR_HEIGHT}"/>"#).unwrap();
+                    writeln!(group, r#"<rect x="{}" y="{top}" width="{half}" height="{BAR_HEIGHT}"/>"#, half + BROKEN_GAP).unwrap();
+                }
+            }
+        }
+        group.push_str("</g>\n");
+
+        if self.lines.iter().any(Line::is_changing) {
+            group.push_str("<g fill=\"none\" stroke=\"#b22\" stroke-width=\"2\">\n");
+            for (i, line) in self.lines.iter().rev().enumerate() {
+                let (cx, cy) = (BAR_WIDTH + MARKER_SPACE / 2, i * LINE_PITCH + BAR_HEIGHT / 2);
+                match line {
+                    Line::ChangingYang => writeln!(group, r#"<circle cx="{cx}" cy="{cy}" r="6"/>"#).unwrap(),
+                    Line::ChangingYin => writeln!(group, r#"<path d="M{} {} L{} {} M{} {} L{} {}"/>"#,
+                        cx - 5, cy - 5, cx + 5, cy + 5, cx - 5, cy + 5, cx + 5, cy - 5).unwrap(),
+                    _ => {}
+                }
+            }
+            group.push_str("</g>\n");
+        }
+
+        group.push_str("<g font-family=\"serif\" font-size=\"14\" fill=\"#222\">\n");
+        if trigram_labels {
+            // level with the middle line of each trigram
+            for (trigram, middle) in [(Hexagram::calculate_trigram(&self.lines[3..6]), 1), (Hexagram::calculate_trigram(&self.lines[0..3]), 4)] {
+                writeln!(group, r#"<text x="{}" y="{}">{} {}</text>"#, BAR_WIDTH + MARKER_SPACE,
+                    middle * LINE_PITCH + BAR_HEIGHT / 2 + 5, Hexagram::trigram_glyph(trigram), Hexagram::TRIGRAMS[trigram]).unwrap();
+            }
+        }
+        writeln!(group, r#"<text x="{}" y="{}" text-anchor="middle">{} {}</text>"#,
+            BAR_WIDTH / 2, LINES_HEIGHT + CAPTION_HEIGHT - 6, self.king_wen_number, self.glyph()).unwrap();
+        group.push_str("</g>\n</g>\n");
+        group
+    }
+}
+
+/// How wide one hexagram is, markers and labels included
+fn svg_width(trigram_labels: bool) -> usize {
+    BAR_WIDTH + MARKER_SPACE + if trigram_labels { LABEL_WIDTH } else { 0 }
+}
+
+fn svg_start(width: usize, height: usize) -> String {
+    format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SNAPSHOT: &str = "src/snapshots/hexagrams.svg.txt";
+
+    /// Every hexagram with its trigrams, then a reading with moving lines of both kinds
+    fn all_svgs() -> String {
+        let mut all = String::new();
+        for binary in 0..64 {
+            let hexagram = Hexagram::from_lines(std::array::from_fn(|i| {
+                if binary & (1 << i) != 0 { Line::StaticYang } else { Line::StaticYin }
+            }));
+            all.push_str(&format!("<!-- hexagram {} -->\n{}", hexagram.king_wen_number, hexagram.to_svg(true)));
+        }
+        let present = Hexagram::from_lines(Hexagram::parse_lines("8 9 7 6 8 7").unwrap());
+        all.push_str(&format!("<!-- reading 18 to 56 -->\n{}", present.reading_svg(present.change().as_ref(), false)));
+        all
+    }
+
+    // UPDATE_SNAPSHOTS=1 cargo test writes the snapshot again after a deliberate change
+    #[test]
+    fn test_svg_snapshots() {
+        let svgs = all_svgs();
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::write(SNAPSHOT, &svgs).unwrap();
+        }
+        let snapshot = std::fs::read_to_string(SNAPSHOT).unwrap();
+        for (actual, expected) in svgs.split("<!-- ").zip(snapshot.split("<!-- ")) {
+            assert_eq!(actual, expected);
+        }
+        assert_eq!(svgs, snapshot);
+    }
+
+    #[test]
+    fn test_svg() {
+        // 18 with lines 2 and 4 moving
+        let present = Hexagram::from_lines(Hexagram::parse_lines("8 9 7 6 8 7").unwrap());
+        let svg = present.to_svg(true);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"306\" height=\"202\" viewBox=\"0 0 306 202\">\n"));
+        assert!(svg.ends_with("</svg>\n"));
+        // three solid lines and three broken ones
+        assert_eq!(svg.matches("<rect ").count(), 9);
+        assert_eq!(svg.matches("<circle ").count(), 1);
+        // the cross is on line 4, the third from the top
+        assert!(svg.contains(r#"<path d="M133 50 L143 60 M133 60 L143 50"/>"#));
+        assert!(svg.contains(">☶ Mountain</text>") && svg.contains(">☴ Wind</text>"));
+        assert!(svg.contains(">18 ䷑</text>"));
+        assert!(!present.to_svg(false).contains("Mountain"));
+
+        // the future hexagram is only drawn if there is one
+        let reading = present.reading_svg(present.change().as_ref(), false);
+        assert!(reading.contains(">56 ䷷</text>") && reading.contains("stroke=\"#222\""));
+        assert_eq!(reading.matches("<rect ").count(), 18);
+        let unchanging = Hexagram::from_lines([Line::StaticYang; 6]);
+        assert_eq!(unchanging.reading_svg(None, true), unchanging.to_svg(true));
+        assert!(!unchanging.to_svg(true).contains("stroke=\"#b22\""));
+    }
+}
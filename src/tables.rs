@@ -0,0 +1,84 @@
+//! The tables the hexagrams are looked up in. They're kept apart from Hexagram, with nothing
+//! to depend on, so build.rs can check the bundled translation against them too.
+
+/// Trigram names indexed by their binary value, bottom line = bit 0
+pub const TRIGRAMS: [&str; 8] = [
+    "Earth",      // 000 - kun, 坤
+    "Thunder",    // 001 - zhen, 震
+    "Water",      // 010 - kan, 坎
+    "Lake",       // 011 - dui, 兌
+    "Mountain",   // 100 - gen, 艮
+    "Fire",       // 101 - li, 離
+    "Wind",       // 110 - xun, 巽
+    "Heaven"      // 111 - qian, 乾
+];
+
+/// Complete mapping from binary index (0-63) to King Wen hexagram numbers (1-64).
+/// Binary index is calculated from bottom line = bit 0, top line = bit 5
+/// where Yin = 0, Yang = 1
+pub const KING_WEN_SEQUENCE: [u8; 64] = [
+    2,  // 000000 ☷☷ Kun (The Receptive)
+    24, // 000001 ☷☳ Fu (Return)
+    7,  // 000010 ☷☵ Shi (The Army) 
+    19, // 000011 ☷☱ Lin (Approach)
+    15, // 000100 ☷☶ Qian (Modesty)
+    36, // 000101 ☷☲ Ming Yi (Darkening of the Light)
+    46, // 000110 ☷☴ Sheng (Pushing Upward)
+    11, // 000111 ☷☰ Tai (Peace)
+    16, // 001000 ☳☷ Yu (Enthusiasm)
+    51, // 001001 ☳☳ Zhen (The Arousing)
+    40, // 001010 ☳☵ Jie (Deliverance)
+    54, // 001011 ☳☱ Gui Mei (The Marrying Maiden)
+    62, // 001100 ☳☶ Xiao Guo (Preponderance of the Small)
+    55, // 001101 ☳☲ Feng (Abundance)
+    32, // 001110 ☳☴ Heng (Duration)
+    34, // 001111 ☳☰ Da Zhuang (The Power of the Great)
+    8,  // 010000 ☵☷ Pi (Holding Together)
+    3,  // 010001 ☵☳ Zhun (Difficulty at the Beginning)
+    29, // 010010 ☵☵ Kan (The Abysmal)
+    60, // 010011 ☵☱ Jie (Limitation)
+    39, // 010100 ☵☶ Jian (Obstruction)
+    63, // 010101 ☵☲ Ji Ji (After Completion)
+    48, // 010110 ☵☴ Jing (The Well)
+    5,  // 010111 ☵☰ Xu (Waiting)
+    45, // 011000 ☱☷ Cui (Gathering Together)
+    17, // 011001 ☱☳ Sui (Following)
+    47, // 011010 ☱☵ Kun (Oppression)
+    58, // 011011 ☱☱ Dui (The Joyous)
+    31, // 011100 ☱☶ Xian (Influence)
+    49, // 011101 ☱☲ Ge (Revolution)
+    28, // 011110 ☱☴ Da Guo (Preponderance of the Great)
+    43, // 011111 ☱☰ Guai (Breakthrough)
+    23, // 100000 ☶☷ Po (Splitting Apart)
+    27, // 100001 ☶☳ Yi (The Corners of the Mouth)
+    4,  // 100010 ☶☵ Meng (Youthful Folly)
+    41, // 100011 ☶☱ Sun (Decrease)
+    52, // 100100 ☶☶ Gen (Keeping Still)
+    22, // 100101 ☶☲ Pi (Grace)
+    18, // 100110 ☶☴ Gu (Work on What Has Been Spoiled)
+    26, // 100111 ☶☰ Da Xu (The Taming Power of the Great)
+    35, // 101000 ☲☷ Jin (Progress)
+    21, // 101001 ☲☳ Shi He (Biting Through)
+    64, // 101010 ☲☵ Wei Ji (Before Completion)
+    38, // 101011 ☲☱ Kui (Opposition)
+    56, // 101100 ☲☶ Lu (The Wanderer)
+    30, // 101101 ☲☲ Li (The Clinging)
+    50, // 101110 ☲☴ Ding (The Cauldron)
+    14, // 101111 ☲☰ Da You (Possession in Great Measure)
+    20, // 110000 ☴☷ Guan (Contemplation)
+    42, // 110001 ☴☳ Yi (Increase)
+    59, // 110010 ☴☵ Huan (Dispersion)
+    61, // 110011 ☴☱ Zhong Fu (Inner Truth)
+    53, // 110100 ☴☶ Jian (Development)
+    37, // 110101 ☴☲ Jia Ren (The Family)
+    57, // 110110 ☴☴ Xun (The Gentle)
+    9,  // 110111 ☴☰ Xiao Xu (The Taming Power of the Small)
+    12, // 111000 ☰☷ Pi (Standstill)
+    25, // 111001 ☰☳ Wu Wang (Innocence)
+    6,  // 111010 ☰☵ Song (Conflict)
+    10, // 111011 ☰☱ Lu (Treading)
+    33, // 111100 ☰☶ Dun (Retreat)
+    13, // 111101 ☰☲ Tong Ren (Fellowship with Men)
+    44, // 111110 ☰☴ Gou (Coming to Meet)
+    1   // 111111 ☰☰ Qian (The Creative)
+];
@@ -0,0 +1,182 @@
+//! Each hexagram has some text associated with it - we load different translations from different files
+
+use std::collections::HashMap;
+use std::fs;
+
+use crate::error::IChingError;
+use crate::validation;
+
+pub use crate::translation_file::{
+    Commentary, HexagramData, HexagramLine, HexagramName, IChingData, IChingTranslation, Metadata, Trigram, Trigrams,
+};
+
+/// Load a translation file and check it against the schema, then check that the numbers,
+/// trigrams and lines agree with each hexagram. All the problems are reported together.
+pub fn load_hexagram_data(filename: &str) -> Result<HashMap<u8, HexagramData>, IChingError> {
+    Ok(load_iching_data(filename)?.into_translation())
+}
+
+/// Like load_hexagram_data, but keeps the metadata
+pub fn load_iching_data(filename: &str) -> Result<IChingData, IChingError> {
+    iching_data_from_json(serde_json::from_str(&fs::read_to_string(filename)?)?)
+}
+
+/// Check and read a whole translation that's already been parsed as JSON
+pub fn iching_data_from_json(json: serde_json::Value) -> Result<IChingData, IChingError> {
+    // partial files are checked more loosely, and only make sense over the translation they name
+    if let Some(base) = json["metadata"]["overlays"].as_str() {
+        return Err(IChingError::DataError(format!(
+            "this file overlays {} and can't be used on its own; load it with overlay::Overlay", base)));
+    }
+
+    let mut problems = validation::schema_problems(&json);
+    // a file that breaks the schema may still be readable, and then it's worth checking further
+    let iching_data = match serde_json::from_value::<IChingData>(json) {
+        Ok(data) => data,
+        Err(e) if problems.is_empty() => return Err(e.into()),
+        Err(_) => return Err(IChingError::ValidationError(problems)),
+    };
+    problems.extend(validation::semantic_problems(&iching_data));
+    if !problems.is_empty() {
+        return Err(IChingError::ValidationError(problems));
+    }
+
+    Ok(iching_data)
+}
+
+/// The default translation, compiled in so the binary works from any directory.
+/// build.rs runs every check a loaded translation gets on it, so a broken file fails the build.
+pub const EMBEDDED_TRANSLATION: &str = include_str!("../data/wilhelm_baynes.json");
+
+/// The compiled-in translation, with its metadata. It's read the same way as any other file,
+/// hence the Result, but build.rs has already made sure it passes.
+pub fn embedded_iching_data() -> Result<IChingData, IChingError> {
+    iching_data_from_json(serde_json::from_str(EMBEDDED_TRANSLATION)?)
+}
+
+/// A translation and the name it goes by
+pub type NamedTranslation<'tr> = (&'tr str, &'tr IChingTranslation);
+
+/// Translations by name, in the order they're shown side by side
+pub type TranslationSet<'tr> = Vec<NamedTranslation<'tr>>;
+
+/// Where a reading's texts come from: one translation, and optionally a second one for
+/// whatever the first is missing. Texts come with the name of the translation they were found in,
+/// and how that's shown is up to the renderers.
+#[derive(Debug, Clone, Copy)]
+pub struct TextLookup<'a> {
+    /// What the main translation is called, to say when a text is missing from it
+    pub name: &'a str,
+    /// Where texts are looked for first
+    pub translation: &'a IChingTranslation,
+    /// Where to look for anything the main translation doesn't have, and what it's called
+    pub fallback: Option<(&'a str, &'a IChingTranslation)>,
+}
+
+impl<'a> TextLookup<'a> {
+    /// Look up texts in just the one translation
+    pub fn new(name: &'a str, translation: &'a IChingTranslation) -> Self {
+        TextLookup { name, translation, fallback: None }
+    }
+
+    /// One text of a hexagram, picked out by `get`, and the name of the translation it's from.
+    /// Empty texts count as missing, and None means neither translation has it.
+    pub fn text(&self, number: u8, get: impl Fn(&'a HexagramData) -> Option<&'a str>) -> Option<(&'a str, &'a str)> {
+        let find = |translation: &'a IChingTranslation| translation.get(&number)
+            .and_then(&get)
+            .filter(|text| !text.trim().is_empty());
+
+        find(self.translation).map(|text| (text, self.name))
+            .or_else(|| self.fallback.and_then(|(name, fallback)| Some((find(fallback)?, name))))
+    }
+
+    /// The hexagram's English name, if either translation has one
+    pub fn name(&self, number: u8) -> Option<&'a str> {
+        let find = |translation: &'a IChingTranslation| translation.get(&number)
+            .map(|data| data.name.english.as_str())
+            .filter(|name| !name.trim().is_empty());
+        find(self.translation).or_else(|| find(self.fallback?.1))
+    }
+
+    /// All the hexagram's names, Chinese and pinyin included, if either translation has them
+    pub fn hexagram_name(&self, number: u8) -> Option<&'a HexagramName> {
+        self.translation.get(&number).or_else(|| self.fallback?.1.get(&number)).map(|data| &data.name)
+    }
+
+    /// "18: Work on What Has Been Spoiled", or just "18" if there's no name
+    pub fn title(&self, number: u8) -> String {
+        match self.name(number) {
+            Some(name) => format!("{}: {}", number, name),
+            None => number.to_string(),
+        }
+    }
+
+    /// The judgement, and where it's from
+    pub fn judgement(&self, number: u8) -> Option<(&'a str, &'a str)> {
+        self.text(number, |data| Some(&data.judgement))
+    }
+
+    /// The image, and where it's from
+    pub fn image(&self, number: u8) -> Option<(&'a str, &'a str)> {
+        self.text(number, |data| Some(&data.image))
+    }
+
+    /// The text of a line, 1 to 6 from the bottom
+    pub fn line(&self, number: u8, position: u8) -> Option<(&'a str, &'a str)> {
+        self.text(number, |data| data.lines.get((position as usize).checked_sub(1)?).map(|line| line.text.as_str()))
+    }
+
+    /// The "use nines" or "use sixes" text
+    pub fn all_lines_changing(&self, number: u8) -> Option<(&'a str, &'a str)> {
+        self.text(number, |data| data.all_lines_changing.as_deref())
+    }
+
+    /// Whether either translation has a text for all lines changing
+    pub fn has_all_lines_changing(&self, number: u8) -> bool {
+        self.all_lines_changing(number).is_some()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_translation() {
+        let data = embedded_iching_data().unwrap();
+        assert_eq!(data.source(), Some("Wilhelm/Baynes"));
+        assert_eq!(data.into_translation().len(), 64);
+    }
+
+    #[test]
+    fn test_text_lookup_fallback() {
+        let wilhelm = load_hexagram_data("data/wilhelm_baynes.json").unwrap();
+        let mut partial = wilhelm.clone();
+        partial.retain(|number, _| *number < 10);
+        partial.get_mut(&3).unwrap().judgement = String::new();
+        partial.get_mut(&4).unwrap().lines.truncate(2);
+        let empty = IChingTranslation::new();
+
+        let lookup = TextLookup::new("Partial", &partial);
+        assert_eq!(lookup.judgement(1), Some((wilhelm[&1].judgement.as_str(), "Partial")));
+        assert_eq!(lookup.judgement(3), None);
+        assert_eq!(lookup.line(4, 2), Some((wilhelm[&4].lines[1].text.as_str(), "Partial")));
+        assert_eq!(lookup.line(4, 3), None);
+        assert_eq!(lookup.line(4, 0), None);
+        assert_eq!(lookup.title(18), "18");
+        assert_eq!(lookup.title(1), "1: The Creative");
+
+        let lookup = TextLookup { fallback: Some(("Wilhelm/Baynes", &wilhelm)), ..lookup };
+        assert_eq!(lookup.judgement(1), Some((wilhelm[&1].judgement.as_str(), "Partial")));
+        assert_eq!(lookup.judgement(3), Some((wilhelm[&3].judgement.as_str(), "Wilhelm/Baynes")));
+        assert_eq!(lookup.line(4, 3), Some((wilhelm[&4].lines[2].text.as_str(), "Wilhelm/Baynes")));
+        assert_eq!(lookup.title(18), "18: Work on What Has Been Spoiled");
+
+        // a fallback that doesn't have it either
+        let lookup = TextLookup { fallback: Some(("Empty", &empty)), ..lookup };
+        assert_eq!(lookup.judgement(3), None);
+        assert!(!lookup.has_all_lines_changing(3));
+        assert!(lookup.has_all_lines_changing(1));
+    }
+}
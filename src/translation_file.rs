@@ -0,0 +1,126 @@
+//! The translation file format, see data/hexagrams_schema.json. This only needs serde,
+//! so build.rs can read the bundled translation with it too.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// What a hexagram is called
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HexagramName {
+    /// e.g. "The Creative"
+    pub english: String,
+    /// In simplified characters
+    pub chinese: Option<String>,
+    /// Pinyin romanization
+    pub pinyin: Option<String>,
+    /// Wade-Giles romanization
+    pub wade_giles: Option<String>,
+}
+
+/// One of the hexagram's two trigrams, as the translation names it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Trigram {
+    /// The English name, e.g. "Heaven"
+    pub name: String,
+    /// Its element or nature, e.g. "strong"
+    pub element: Option<String>,
+}
+
+/// The upper and lower trigrams of a hexagram
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Trigrams {
+    /// Lines 4 to 6
+    pub upper: Trigram,
+    /// Lines 1 to 3
+    pub lower: Trigram,
+}
+
+/// The translator's commentary on the image and the judgement
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Commentary {
+    /// Commentary on the image
+    pub image: Option<String>,
+    /// Commentary on the judgement
+    pub judgement: Option<String>,
+}
+
+/// The text of one line of a hexagram
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HexagramLine {
+    /// 1 to 6 from the bottom
+    pub position: u8,
+    /// "yin" or "yang"
+    #[serde(rename = "type")]
+    pub line_type: String,
+    /// What the line says when it's moving
+    pub text: String,
+    /// Commentary on the line
+    pub commentary: Option<String>,
+}
+
+/// Everything a translation says about one hexagram
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HexagramData {
+    /// 1 to 64
+    pub king_wen_number: u8,
+    /// 0 to 63, with the bottom line as bit 0 and yang as 1
+    pub fu_xi_number: Option<u8>,
+    /// What it's called
+    pub name: HexagramName,
+    /// Its two trigrams
+    pub trigrams: Trigrams,
+    /// The image (Xiang)
+    pub image: String,
+    /// The judgement (Tuan)
+    pub judgement: String,
+    /// The translator's commentary, if there is any
+    pub commentary: Option<Commentary>,
+    /// The six lines, bottom first
+    pub lines: Vec<HexagramLine>,
+    /// The seventh text, read when all six lines are moving; only the Creative and the Receptive have one
+    pub all_lines_changing: Option<String>,
+    /// Key concepts and themes
+    pub keywords: Option<Vec<String>>,
+    /// Notes about the hexagram's place in the sequence
+    pub sequence_notes: Option<String>,
+}
+
+/// About the translation as a whole
+#[derive(Debug, Deserialize)]
+pub struct Metadata {
+    /// The name the translation goes by, e.g. "Wilhelm/Baynes"
+    pub translation_source: Option<String>,
+    /// The version of the file
+    pub version: Option<String>,
+    /// Anything else worth knowing about it
+    pub notes: Option<String>,
+    /// The translation_source of the translation this file fills in, for partial files
+    pub overlays: Option<String>,
+}
+
+/// A whole translation file, see data/hexagrams_schema.json
+#[derive(Debug, Deserialize)]
+pub struct IChingData {
+    /// Every hexagram in the file, in any order
+    pub hexagrams: Vec<HexagramData>,
+    /// What the file says about itself
+    pub metadata: Option<Metadata>,
+}
+
+/// Hexagram texts keyed by King Wen number
+pub type IChingTranslation = HashMap<u8, HexagramData>;
+
+impl IChingData {
+    /// The name the translation goes by, from its metadata
+    pub fn source(&self) -> Option<&str> {
+        self.metadata.as_ref()?.translation_source.as_deref()
+    }
+
+    /// The hexagrams keyed by King Wen number
+    pub fn into_translation(self) -> IChingTranslation {
+        self.hexagrams.into_iter()
+            .map(|hexagram| (hexagram.king_wen_number, hexagram))
+            .collect()
+    }
+}
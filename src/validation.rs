@@ -0,0 +1,228 @@
+//! Checking translation files: first against data/hexagrams_schema.json, then for the things
+//! a schema can't say, like whether the trigram names and line types fit the hexagram.
+//! Every problem is collected so a translation can be fixed in one pass.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde_json::Value;
+
+// only the tables and the file format, so build.rs can share this file
+use crate::tables::{KING_WEN_SEQUENCE, TRIGRAMS};
+use crate::translation_file::IChingData;
+
+/// The schema every translation file follows
+pub const SCHEMA: &str = include_str!("../data/hexagrams_schema.json");
+
+/// One thing wrong with a translation file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationProblem {
+    /// The King Wen number of the hexagram it's in, if it's in one and the number is known
+    pub hexagram: Option<u8>,
+    /// JSON pointer to the offending value, e.g. /hexagrams/17/lines/2/type
+    pub path: String,
+    /// What's wrong with it
+    pub message: String,
+}
+
+impl fmt::Display for ValidationProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.hexagram {
+            Some(number) => write!(f, "hexagram {} at {}: {}", number, self.path, self.message),
+            None => write!(f, "{}: {}", if self.path.is_empty() { "/" } else { &self.path }, self.message),
+        }
+    }
+}
+
+/// The King Wen number of the hexagram at a JSON pointer like /hexagrams/17/..., if there is one
+fn hexagram_at(json: &Value, path: &str) -> Option<u8> {
+    let index: usize = path.strip_prefix("/hexagrams/")?.split('/').next()?.parse().ok()?;
+    json["hexagrams"][index]["king_wen_number"].as_u64().and_then(|n| u8::try_from(n).ok())
+}
+
+/// Everything that doesn't match the schema
+pub fn schema_problems(json: &Value) -> Vec<ValidationProblem> {
+    let schema: Value = serde_json::from_str(SCHEMA).expect("the bundled schema is valid JSON");
+    problems_against(&schema, json)
+}
+
+/// Everything that doesn't match the schema for overlays, see overlay_schema
+pub fn overlay_schema_problems(json: &Value) -> Vec<ValidationProblem> {
+    problems_against(&overlay_schema(), json)
+}
+
+/// The schema with nothing required but the numbers that say where things go,
+/// and no limits on how many hexagrams or lines there are. Partial translations are
+/// checked against this.
+pub fn overlay_schema() -> Value {
+    fn relax(schema: &mut Value) {
+        let Value::Object(object) = schema else { return };
+        object.remove("minItems");
+        object.remove("maxItems");
+        if let Some(Value::Array(required)) = object.get_mut("required") {
+            required.retain(|field| matches!(field.as_str(), Some("hexagrams" | "king_wen_number" | "position")));
+        }
+        object.values_mut().for_each(relax);
+    }
+
+    let mut schema: Value = serde_json::from_str(SCHEMA).expect("the bundled schema is valid JSON");
+    relax(&mut schema);
+    schema
+}
+
+/// Everything in the JSON that doesn't match the given schema
+pub fn problems_against(schema: &Value, json: &Value) -> Vec<ValidationProblem> {
+    let validator = jsonschema::validator_for(schema).expect("the bundled schema is a valid JSON schema");
+
+    validator.iter_errors(json).map(|error| {
+        let path = error.instance_path.to_string();
+        // the messages start with the offending value, which for a whole hexagram is far too much
+        let mut message = error.to_string();
+        let summary = match error.instance.as_ref() {
+            Value::Array(items) => Some(format!("array of {} items", items.len())),
+            Value::Object(_) => Some("object".to_string()),
+            _ => None,
+        };
+        if let Some(summary) = summary {
+            let instance = error.instance.to_string();
+            if let Some(rest) = message.strip_prefix(&instance) {
+                message = format!("{}{}", summary, rest);
+            }
+        }
+        ValidationProblem { hexagram: hexagram_at(json, &path), path, message }
+    }).collect()
+}
+
+/// Everything the schema can't catch: repeated hexagrams, and numbers, trigrams and lines
+/// that don't agree with the hexagram they're filed under
+pub fn semantic_problems(data: &IChingData) -> Vec<ValidationProblem> {
+    let mut problems = Vec::new();
+    let mut seen: HashMap<u8, usize> = HashMap::new();
+
+    for (index, hexagram) in data.hexagrams.iter().enumerate() {
+        let number = hexagram.king_wen_number;
+        let mut problem = |field: &str, message: String| problems.push(ValidationProblem {
+            hexagram: Some(number),
+            path: format!("/hexagrams/{}{}", index, field),
+            message,
+        });
+
+        if let Some(first) = seen.insert(number, index) {
+            problem("/king_wen_number", format!("King Wen number {} is already used by /hexagrams/{}", number, first));
+        }
+
+        // the rest needs the hexagram's lines, so the number has to be a real one
+        let Some(binary) = KING_WEN_SEQUENCE.iter().position(|n| *n == number) else {
+            continue;
+        };
+
+        if let Some(fu_xi) = hexagram.fu_xi_number
+            && fu_xi as usize != binary {
+            problem("/fu_xi_number", format!("is {} but hexagram {} is {}", fu_xi, number, binary));
+        }
+
+        for (field, trigram, expected) in [
+            ("upper", &hexagram.trigrams.upper, TRIGRAMS[binary >> 3]),
+            ("lower", &hexagram.trigrams.lower, TRIGRAMS[binary & 0b111]),
+        ] {
+            if !trigram.name.eq_ignore_ascii_case(expected) {
+                problem(&format!("/trigrams/{}/name", field), format!("is {} but should be {}", trigram.name, expected));
+            }
+        }
+
+        for (i, line) in hexagram.lines.iter().enumerate() {
+            if line.position as usize != i + 1 {
+                problem(&format!("/lines/{}/position", i), format!("is {} but should be {}", line.position, i + 1));
+            }
+            // anything but yin or yang is already a schema problem
+            if i >= 6 {
+                continue;
+            }
+            let expected = if binary & (1 << i) != 0 { "yang" } else { "yin" };
+            if matches!(line.line_type.as_str(), "yin" | "yang") && line.line_type != expected {
+                problem(&format!("/lines/{}/type", i), format!("is {} but line {} is {}", line.line_type, i + 1, expected));
+            }
+        }
+
+        if hexagram.all_lines_changing.is_some() && !matches!(number, 1 | 2) {
+            problem("/all_lines_changing", "only hexagrams 1 and 2 have a text for all lines changing".to_string());
+        }
+    }
+
+    problems
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wilhelm() -> Value {
+        serde_json::from_str(&std::fs::read_to_string("data/wilhelm_baynes.json").unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_bundled_translation_is_valid() {
+        let json = wilhelm();
+        assert_eq!(schema_problems(&json), vec![]);
+        let data: IChingData = serde_json::from_value(json).unwrap();
+        assert_eq!(semantic_problems(&data), vec![]);
+    }
+
+    #[test]
+    fn test_schema_problems_have_paths() {
+        let mut json = wilhelm();
+        json["hexagrams"][17]["lines"][2]["type"] = "yinn".into();
+        json["hexagrams"][3].as_object_mut().unwrap().remove("judgement");
+        json["hexagrams"][5]["colour"] = "red".into();
+
+        let problems = schema_problems(&json);
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        assert!(problems.iter().any(|p| p.hexagram == Some(18) && p.path == "/hexagrams/17/lines/2/type"));
+        assert!(problems.iter().any(|p| p.hexagram == Some(4) && p.path == "/hexagrams/3" && p.message.contains("judgement")));
+        assert!(problems.iter().any(|p| p.hexagram == Some(6) && p.message.contains("colour")));
+    }
+
+    #[test]
+    fn test_semantic_problems() {
+        let mut json = wilhelm();
+        // 18 is Mountain over Wind, 8 7 7 8 8 7 from the bottom
+        json["hexagrams"][17]["fu_xi_number"] = 5.into();
+        json["hexagrams"][17]["trigrams"]["upper"]["name"] = "Lake".into();
+        json["hexagrams"][17]["lines"][0]["type"] = "yang".into();
+        json["hexagrams"][17]["all_lines_changing"] = "no".into();
+        // 3 twice, and no 21
+        json["hexagrams"][20] = json["hexagrams"][2].clone();
+
+        let data: IChingData = serde_json::from_value(json).unwrap();
+        let problems: Vec<String> = semantic_problems(&data).iter().map(|p| p.to_string()).collect();
+        assert_eq!(problems, vec![
+            "hexagram 18 at /hexagrams/17/fu_xi_number: is 5 but hexagram 18 is 38",
+            "hexagram 18 at /hexagrams/17/trigrams/upper/name: is Lake but should be Mountain",
+            "hexagram 18 at /hexagrams/17/lines/0/type: is yang but line 1 is yin",
+            "hexagram 18 at /hexagrams/17/all_lines_changing: only hexagrams 1 and 2 have a text for all lines changing",
+            "hexagram 3 at /hexagrams/20/king_wen_number: King Wen number 3 is already used by /hexagrams/2",
+        ]);
+    }
+
+    #[test]
+    fn test_load_reports_every_problem() {
+        use crate::error::IChingError;
+        use crate::translation::iching_data_from_json;
+
+        let mut json = wilhelm();
+        json["hexagrams"][0]["fu_xi_number"] = 0.into();
+        json["hexagrams"][1]["lines"][5]["type"] = "line".into();
+        json["hexagrams"].as_array_mut().unwrap().pop();
+
+        // loading a file is reading it and then this, so there's no need for a file
+        let error = iching_data_from_json(json).unwrap_err();
+        let IChingError::ValidationError(problems) = &error else { panic!("{}", error) };
+        // the line type and the missing hexagram break the schema, and the file is still read
+        // far enough to find the wrong Fu Xi number
+        assert_eq!(problems.len(), 3, "{}", error);
+        assert!(error.to_string().starts_with("Data validation error: 3 problems\n  "));
+        assert!(error.to_string().contains("hexagram 1 at /hexagrams/0/fu_xi_number: is 0 but hexagram 1 is 63"));
+        assert!(error.to_string().contains("/hexagrams: array of 63 items has less than 64 items"));
+    }
+}